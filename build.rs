@@ -12,11 +12,15 @@ fn main() {
     CFG.exported_header_dirs.push(&xapian_include_dir);
 
     let sources = vec!["src/lib.rs"];
-    cxx_build::bridges(sources)
+    let mut build = cxx_build::bridges(sources);
+    build
         .file("xapian-bind.cc")
         .flag_if_supported("-std=c++14")
-        .flag_if_supported("-Wno-deprecated-declarations")
-        .compile("xapian-rusty");
+        .flag_if_supported("-Wno-deprecated-declarations");
+    if env::var_os("CARGO_FEATURE_TEST_THROW").is_some() {
+        build.define("XAPIAN_RUSTY_TEST_THROW", None);
+    }
+    build.compile("xapian-rusty");
 
     println!("cargo:rustc-link-lib=xapianm");
     println!("cargo:rustc-link-lib=m");
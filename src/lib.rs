@@ -1,34 +1,267 @@
+use std::cell::OnceCell;
 use std::error::Error as StdError;
 use std::fmt::{self, Display};
-use std::io;
+use std::fs::File;
+use std::io::{self, Read};
+use std::marker::PhantomData;
+use std::num::NonZeroU32;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::str::{FromStr, SplitWhitespace};
+use std::sync::Arc;
+use std::ops::{BitOr, BitOrAssign, Deref, DerefMut, RangeInclusive};
 
 use cxx::UniquePtr;
 
-pub const BRASS: i8 = 1;
-pub const CHERT: i8 = 2;
-pub const IN_MEMORY: i8 = 3;
-pub const UNKNOWN: i8 = 0;
-
 /** Create database if it doesn't already exist.
  *
  *  If no opening mode is specified, this is the default.
  */
-pub const DB_CREATE_OR_OPEN: i8 = 0x00;
+pub const DB_CREATE_OR_OPEN: DbAction = DbAction::CreateOrOpen;
 
 /** Create database if it doesn't already exist, or overwrite if it does. */
-pub const DB_CREATE_OR_OVERWRITE: i8 = 0x01;
+pub const DB_CREATE_OR_OVERWRITE: DbAction = DbAction::CreateOrOverwrite;
 
 /** Create a new database.
  *
  *  If the database already exists, an exception will be thrown.
  */
-pub const DB_CREATE: i8 = 0x02;
+pub const DB_CREATE: DbAction = DbAction::Create;
 
 /** Open an existing database.
  *
  *  If the database doesn't exist, an exception will be thrown.
  */
-pub const DB_OPEN: i8 = 0x03;
+pub const DB_OPEN: DbAction = DbAction::Open;
+
+/// What to do depending on whether the database already exists
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DbAction {
+    /// Create database if it doesn't already exist, the default
+    #[default]
+    CreateOrOpen = 0x00,
+    /// Create database if it doesn't already exist, or overwrite if it does
+    CreateOrOverwrite = 0x01,
+    /// Create a new database, fail if it already exists
+    Create = 0x02,
+    /// Open an existing database, fail if it doesn't exist
+    Open = 0x03,
+}
+
+/// Flags for opening a database, the `DB_*` constants of Xapian.
+///
+/// Combine them with `|`, e.g. `DbFlags::NO_SYNC | DbFlags::DANGEROUS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DbFlags(i32);
+
+impl DbFlags {
+    /** Don't attempt to ensure changes have hit disk.
+     *
+     *  Changes may be lost on a crash or power failure, but indexing can be
+     *  significantly faster.
+     */
+    pub const NO_SYNC: DbFlags = DbFlags(0x04);
+
+    /** Try to ensure changes are really written to disk, past the drive's
+     *  write-back cache, where the platform supports it.
+     */
+    pub const FULL_SYNC: DbFlags = DbFlags(0x08);
+
+    /** Update the database in-place.
+     *
+     *  Less I/O and a more compact result, but you can't concurrently search
+     *  while indexing, transactions can't be cancelled, and if indexing ends
+     *  uncleanly the database won't be usable.
+     */
+    pub const DANGEROUS: DbFlags = DbFlags(0x10);
+
+    /** When creating a database, don't create a termlist table.
+     *
+     *  Saves space and time, but document termlists, replacing or deleting
+     *  existing documents and iterating a document's values need it.
+     */
+    pub const NO_TERMLIST: DbFlags = DbFlags(0x20);
+
+    /** If the database is already locked by a writer, wait for the lock
+     *  instead of failing with DatabaseLockError.
+     */
+    pub const RETRY_LOCK: DbFlags = DbFlags(0x40);
+
+    pub const fn empty() -> Self {
+        DbFlags(0)
+    }
+
+    pub const fn bits(self) -> i32 {
+        self.0
+    }
+
+    pub const fn contains(self, other: DbFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for DbFlags {
+    type Output = DbFlags;
+
+    fn bitor(self, rhs: DbFlags) -> DbFlags {
+        DbFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for DbFlags {
+    fn bitor_assign(&mut self, rhs: DbFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Database backend, selected with the `DB_BACKEND_*` flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Backend {
+    /// Open whatever backend an existing database uses, create glass databases
+    #[default]
+    Auto,
+    /// Create a glass database if a new database is created
+    Glass,
+    /// Create a chert database if a new database is created
+    Chert,
+    /// The "in memory" backend, the path is ignored
+    InMemory,
+    /// A stub database file listing other databases
+    Stub,
+}
+
+const DB_BACKEND_MASK: i32 = 0x700;
+
+/// Start of a single file glass database
+const GLASS_SINGLE_FILE_MAGIC: &[u8; 14] = b"\x0f\x0dXapian Glass";
+
+impl Backend {
+    /// The `DB_BACKEND_*` value selecting this backend, a field under `DB_BACKEND_MASK`
+    /// rather than bits which combine
+    pub fn flags(self) -> DbFlags {
+        match self {
+            Backend::Auto => DbFlags::empty(),
+            Backend::Glass => DbFlags(0x100),
+            Backend::Chert => DbFlags(0x200),
+            Backend::Stub => DbFlags(0x300),
+            Backend::InMemory => DbFlags(0x400),
+        }
+    }
+
+    pub fn from_flags(flags: DbFlags) -> Backend {
+        match flags.bits() & DB_BACKEND_MASK {
+            0x100 => Backend::Glass,
+            0x200 => Backend::Chert,
+            0x300 => Backend::Stub,
+            0x400 => Backend::InMemory,
+            _ => Backend::Auto,
+        }
+    }
+
+    /// Backend of the database at `path`, detected from its files the way Xapian does
+    pub fn detect(path: impl AsRef<Path>) -> Option<Backend> {
+        let path = path.as_ref();
+        if path.is_file() {
+            // a single file glass database (DBCOMPACT_SINGLE_FILE) or a stub database file
+            let mut magic = [0u8; 14];
+            let is_glass = File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && &magic == GLASS_SINGLE_FILE_MAGIC;
+            Some(if is_glass {
+                Backend::Glass
+            } else {
+                Backend::Stub
+            })
+        } else if path.join("iamglass").is_file() {
+            Some(Backend::Glass)
+        } else if path.join("iamchert").is_file() {
+            Some(Backend::Chert)
+        } else if path.join("XAPIANDB").is_file() {
+            // a stub directory, only if the directory isn't a database itself
+            Some(Backend::Stub)
+        } else {
+            None
+        }
+    }
+
+    // The backend actually in use once a database has been opened with `flags`
+    fn in_use(path: &str, flags: DbFlags) -> Option<Backend> {
+        match Backend::from_flags(flags) {
+            Backend::InMemory => Some(Backend::InMemory),
+            _ => Backend::detect(path),
+        }
+    }
+}
+
+/// Options for opening a `WritableDatabase`, in the manner of `std::fs::OpenOptions`,
+/// e.g. `OpenOptions::new().no_sync(true).dangerous(true).open(path)` for a bulk reindex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OpenOptions {
+    action: DbAction,
+    flags: DbFlags,
+}
+
+impl OpenOptions {
+    pub fn new() -> Self {
+        OpenOptions::default()
+    }
+
+    pub fn action(&mut self, action: DbAction) -> &mut Self {
+        self.action = action;
+        self
+    }
+
+    /// Add raw flags to the ones already set, a backend among them replaces the current one
+    pub fn flags(&mut self, flags: DbFlags) -> &mut Self {
+        self.flags.0 |= flags.0 & !DB_BACKEND_MASK;
+        if flags.0 & DB_BACKEND_MASK != 0 {
+            self.backend(Backend::from_flags(flags));
+        }
+        self
+    }
+
+    pub fn no_sync(&mut self, on: bool) -> &mut Self {
+        self.set(DbFlags::NO_SYNC, on)
+    }
+
+    pub fn full_sync(&mut self, on: bool) -> &mut Self {
+        self.set(DbFlags::FULL_SYNC, on)
+    }
+
+    pub fn dangerous(&mut self, on: bool) -> &mut Self {
+        self.set(DbFlags::DANGEROUS, on)
+    }
+
+    pub fn no_termlist(&mut self, on: bool) -> &mut Self {
+        self.set(DbFlags::NO_TERMLIST, on)
+    }
+
+    pub fn retry_lock(&mut self, on: bool) -> &mut Self {
+        self.set(DbFlags::RETRY_LOCK, on)
+    }
+
+    pub fn backend(&mut self, backend: Backend) -> &mut Self {
+        self.flags.0 = (self.flags.0 & !DB_BACKEND_MASK) | backend.flags().bits();
+        self
+    }
+
+    fn set(&mut self, flag: DbFlags, on: bool) -> &mut Self {
+        if on {
+            self.flags.0 |= flag.0;
+        } else {
+            self.flags.0 &= !flag.0;
+        }
+        self
+    }
+
+    /// The combined `int flags` passed to Xapian
+    pub fn bits(&self) -> i32 {
+        self.action as i32 | self.flags.bits()
+    }
+
+    pub fn open(&self, path: &str) -> Result<WritableDatabase, XError> {
+        WritableDatabase::open(path, self)
+    }
+}
 
 /// Enum of possible query operations
 /// #[repr(i32)]
@@ -248,6 +481,88 @@ pub enum FeatureFlag {
 
 #[cxx::bridge]
 pub(crate) mod ffi {
+    /// The one table of error codes used by both sides of the bridge,
+    /// one code per Xapian 1.4 exception class.
+    #[repr(i8)]
+    enum ErrorCode {
+        Ok = 0,
+        DatabaseModifiedError = -1,
+        DatabaseLockError = -2,
+        LogicError = -3,
+        AssertionError = -4,
+        InvalidArgumentError = -5,
+        InvalidOperationError = -6,
+        UnimplementedError = -7,
+        RuntimeError = -8,
+        DatabaseError = -9,
+        DatabaseCorruptError = -10,
+        DatabaseCreateError = -11,
+        DatabaseOpeningError = -12,
+        DatabaseVersionError = -13,
+        DocNotFoundError = -14,
+        FeatureUnavailableError = -15,
+        InternalError = -16,
+        NetworkError = -17,
+        NetworkTimeoutError = -18,
+        QueryParserError = -19,
+        RangeError = -20,
+        SerialisationError = -21,
+        WildcardError = -22,
+        DatabaseNotFoundError = -23,
+        DatabaseClosedError = -24,
+        /// A std::exception which isn't a Xapian::Error
+        CxxException = -127,
+        Unknown = -128,
+    }
+
+    /// Match details of one MSet entry
+    struct MSetHit {
+        docid: u32,
+        rank: u32,
+        weight: f64,
+        percent: i32,
+    }
+
+    /// Statistics handed to a custom weight, only those asked for are filled in
+    #[derive(Default)]
+    struct FfiWeightStats {
+        collection_size: u32,
+        rset_size: u32,
+        average_length: f64,
+        termfreq: u32,
+        reltermfreq: u32,
+        collection_freq: u32,
+        query_length: u32,
+        wqf: u32,
+        doclength_lower_bound: u32,
+        doclength_upper_bound: u32,
+        wdf_upper_bound: u32,
+        total_length: u64,
+    }
+
+    /// Details of an exception caught on the C++ side, code Ok means no error.
+    #[derive(Default)]
+    struct FfiError {
+        code: ErrorCode,
+        msg: String,
+        context: String,
+        error_string: String,
+    }
+
+    extern "Rust" {
+        type WeightBox;
+        fn weight_clone(weight: &WeightBox) -> Result<Box<WeightBox>>;
+        fn weight_stats_needed(weight: &WeightBox) -> Result<u32>;
+        fn weight_init(weight: &mut WeightBox, factor: f64, stats: &FfiWeightStats) -> Result<()>;
+        fn weight_get_sumpart(weight: &WeightBox, wdf: u32, doclen: u32, uniqterms: u32) -> Result<f64>;
+        fn weight_get_maxpart(weight: &WeightBox) -> Result<f64>;
+        fn weight_get_sumextra(weight: &WeightBox, doclen: u32, uniqterms: u32) -> Result<f64>;
+        fn weight_get_maxextra(weight: &WeightBox) -> Result<f64>;
+
+        type KeyMakerBox;
+        fn key_maker_call(key_maker: &KeyMakerBox, doc: UniquePtr<Document>) -> Result<Vec<u8>>;
+    }
+
     #[namespace = "Xapian"]
     extern "C++" {
         pub(crate) type Database;
@@ -260,75 +575,192 @@ pub(crate) mod ffi {
         pub(crate) type QueryParser;
         pub(crate) type Query;
         pub(crate) type MultiValueKeyMaker;
+        pub(crate) type KeyMaker;
     }
 
     unsafe extern "C++" {
         include!("xapian-rusty/xapian-bind.h");
 
-        pub(crate) fn new_database(err: &mut i8) -> UniquePtr<Database>;
-        pub(crate) fn new_database_with_path(path: &str, db_type: i8, err: &mut i8) -> UniquePtr<Database>;
-        pub(crate) fn database_reopen(db: Pin<&mut Database>, err: &mut i8);
-        pub(crate) fn database_close(db: Pin<&mut Database>, err: &mut i8);
-        pub(crate) fn new_enquire(db: Pin<&mut Database>, err: &mut i8) -> UniquePtr<Enquire>;
-        pub(crate) fn add_database(db: Pin<&mut Database>, add_db: Pin<&mut Database>, err: &mut i8);
-
-        pub(crate) fn new_stem(lang: &str, err: &mut i8) -> UniquePtr<Stem>;
-
-        pub(crate) fn new_writable_database_with_path(path: &str, action: i8, db_type: i8, err: &mut i8) -> UniquePtr<WritableDatabase>;
-        pub(crate) fn commit(db: Pin<&mut WritableDatabase>, err: &mut i8);
-        pub(crate) fn replace_document(db: Pin<&mut WritableDatabase>, unique_term: &str, doc: Pin<&mut Document>, err: &mut i8) -> u32;
-        pub(crate) fn delete_document(db: Pin<&mut WritableDatabase>, unique_term: &str, err: &mut i8);
-        pub(crate) fn get_doccount(db: Pin<&mut WritableDatabase>, err: &mut i8) -> i32;
-
-        pub(crate) fn new_termgenerator(err: &mut i8) -> UniquePtr<TermGenerator>;
-        pub(crate) fn set_stemmer(tg: Pin<&mut TermGenerator>, stem: Pin<&mut Stem>, err: &mut i8);
-        pub(crate) fn set_document(tg: Pin<&mut TermGenerator>, doc: Pin<&mut Document>, err: &mut i8);
-        pub(crate) fn index_text_with_prefix(tg: Pin<&mut TermGenerator>, data: &str, prefix: &str, err: &mut i8);
-        pub(crate) fn index_text(tg: Pin<&mut TermGenerator>, data: &str, err: &mut i8);
-        pub(crate) fn index_int(tg: Pin<&mut TermGenerator>, data: i32, prefix: &str, err: &mut i8);
-        pub(crate) fn index_long(tg: Pin<&mut TermGenerator>, data: i64, prefix: &str, err: &mut i8);
-        pub(crate) fn index_float(tg: Pin<&mut TermGenerator>, data: f32, prefix: &str, err: &mut i8);
-        pub(crate) fn index_double(tg: Pin<&mut TermGenerator>, data: f64, prefix: &str, err: &mut i8);
-
-        pub(crate) fn new_document(err: &mut i8) -> UniquePtr<Document>;
-        pub(crate) fn add_string(doc: Pin<&mut Document>, slot: u32, data: &str, err: &mut i8);
-        pub(crate) fn add_int(doc: Pin<&mut Document>, slot: u32, data: i32, err: &mut i8);
-        pub(crate) fn add_long(doc: Pin<&mut Document>, slot: u32, data: i64, err: &mut i8);
-        pub(crate) fn add_float(doc: Pin<&mut Document>, slot: u32, data: f32, err: &mut i8);
-        pub(crate) fn add_double(doc: Pin<&mut Document>, slot: u32, data: f64, err: &mut i8);
-        pub(crate) fn set_data(doc: Pin<&mut Document>, data: &str, err: &mut i8);
-        pub(crate) fn get_doc_data(doc: Pin<&mut Document>) -> &CxxString;
-        pub(crate) fn add_boolean_term(doc: Pin<&mut Document>, data: &str, err: &mut i8);
-
-        pub(crate) fn get_matches_estimated(set: Pin<&mut MSet>, err: &mut i8) -> i32;
-        pub(crate) fn mset_size(set: Pin<&mut MSet>, err: &mut i8) -> i32;
-        pub(crate) fn get_doc_by_index(set: Pin<&mut MSet>, index: i32, err: &mut i8) -> UniquePtr<Document>;
-
-        pub(crate) fn get_mset(en: Pin<&mut Enquire>, from: i32, size: i32, err: &mut i8) -> UniquePtr<MSet>;
-        pub(crate) fn set_query(en: Pin<&mut Enquire>, query: Pin<&mut Query>, err: &mut i8);
-        pub(crate) fn set_sort_by_key(en: Pin<&mut Enquire>, sorter: Pin<&mut MultiValueKeyMaker>, reverse: bool, err: &mut i8);
-
-        pub(crate) fn new_query_parser(err: &mut i8) -> UniquePtr<QueryParser>;
-        pub(crate) fn set_max_wildcard_expansion(qp: Pin<&mut QueryParser>, limit: i32, err: &mut i8);
-        pub(crate) fn set_stemmer_to_qp(qp: Pin<&mut QueryParser>, stem: Pin<&mut Stem>, err: &mut i8);
-        pub(crate) fn set_database(qp: Pin<&mut QueryParser>, add_db: Pin<&mut Database>, err: &mut i8);
-        pub(crate) fn parse_query(qp: Pin<&mut QueryParser>, query_string: &str, flags: i16, err: &mut i8) -> UniquePtr<Query>;
-        pub(crate) fn parse_query_with_prefix(qp: Pin<&mut QueryParser>, query_string: &str, flags: i16, prefix: &str, err: &mut i8) -> UniquePtr<Query>;
-
-        pub(crate) fn new_query(err: &mut i8) -> UniquePtr<Query>;
-        pub(crate) fn new_query_range(op: i32, slot: u32, begin: f64, end: f64, err: &mut i8) -> UniquePtr<Query>;
-        pub(crate) fn add_right_query(this_q: Pin<&mut Query>, op: i32, q: Pin<&mut Query>, err: &mut i8) -> UniquePtr<Query>;
-        pub(crate) fn new_query_double_with_prefix(prefix: &str, d: f64, err: &mut i8) -> UniquePtr<Query>;
-        pub(crate) fn query_is_empty(this_q: Pin<&mut Query>, err: &mut i8) -> bool;
-        pub(crate) fn get_description(this_q: Pin<&mut Query>) -> &CxxString;
-
-        pub(crate) fn new_multi_value_key_maker(err: &mut i8) -> UniquePtr<MultiValueKeyMaker>;
-        pub(crate) fn add_value_to_multi_value_key_maker(this_m: Pin<&mut MultiValueKeyMaker>, slot: u32, asc_desc: bool, err: &mut i8);
+        pub(crate) fn new_database(err: &mut FfiError) -> UniquePtr<Database>;
+        pub(crate) fn new_database_with_path(path: &str, flags: i32, err: &mut FfiError) -> UniquePtr<Database>;
+        pub(crate) fn database_reopen(db: Pin<&mut Database>, err: &mut FfiError);
+        pub(crate) fn database_close(db: Pin<&mut Database>, err: &mut FfiError);
+        pub(crate) fn database_get_doccount(db: &Database, err: &mut FfiError) -> u32;
+        pub(crate) fn database_get_lastdocid(db: &Database, err: &mut FfiError) -> u32;
+        pub(crate) fn database_get_avlength(db: &Database, err: &mut FfiError) -> f64;
+        pub(crate) fn database_get_total_length(db: &Database, err: &mut FfiError) -> u64;
+        pub(crate) fn database_get_doclength(db: &Database, did: u32, err: &mut FfiError) -> u32;
+        pub(crate) fn database_get_unique_terms(db: &Database, did: u32, err: &mut FfiError) -> u32;
+        pub(crate) fn database_get_termfreq(db: &Database, term: &[u8], err: &mut FfiError) -> u32;
+        pub(crate) fn database_get_collection_freq(db: &Database, term: &[u8], err: &mut FfiError) -> u32;
+        pub(crate) fn database_term_exists(db: &Database, term: &[u8], err: &mut FfiError) -> bool;
+        pub(crate) fn database_get_doclength_lower_bound(db: &Database, err: &mut FfiError) -> u32;
+        pub(crate) fn database_get_doclength_upper_bound(db: &Database, err: &mut FfiError) -> u32;
+        pub(crate) fn database_get_wdf_upper_bound(db: &Database, term: &[u8], err: &mut FfiError) -> u32;
+        pub(crate) fn database_allterms(db: &Database, prefix: &[u8], err: &mut FfiError) -> UniquePtr<TermIter>;
+        pub(crate) fn database_postlist(db: &Database, term: &[u8], err: &mut FfiError) -> UniquePtr<PostingIter>;
+        pub(crate) fn database_positionlist(db: &Database, did: u32, term: &[u8], err: &mut FfiError) -> UniquePtr<PositionIter>;
+        pub(crate) fn database_get_document(db: &Database, did: u32, assume_valid: bool, err: &mut FfiError) -> UniquePtr<Document>;
+        pub(crate) fn database_valuestream(db: &Database, slot: u32, err: &mut FfiError) -> UniquePtr<ValueIter>;
+        pub(crate) fn database_get_value_freq(db: &Database, slot: u32, err: &mut FfiError) -> u32;
+        pub(crate) fn database_get_value_lower_bound(db: &Database, slot: u32, err: &mut FfiError) -> UniquePtr<CxxString>;
+        pub(crate) fn database_get_value_upper_bound(db: &Database, slot: u32, err: &mut FfiError) -> UniquePtr<CxxString>;
+        pub(crate) fn new_enquire(db: Pin<&mut Database>, err: &mut FfiError) -> UniquePtr<Enquire>;
+        pub(crate) fn add_database(db: Pin<&mut Database>, add_db: Pin<&mut Database>, err: &mut FfiError);
+
+        pub(crate) fn new_stem(lang: &str, err: &mut FfiError) -> UniquePtr<Stem>;
+
+        // the feature only exists for the tests, the library itself never calls this
+        #[cfg(feature = "test-throw")]
+        #[allow(dead_code)]
+        pub(crate) fn test_throw(kind: &str, msg: &str, err: &mut FfiError);
+
+        pub(crate) fn new_writable_database_with_flags(path: &str, flags: i32, err: &mut FfiError) -> UniquePtr<WritableDatabase>;
+        pub(crate) fn commit(db: Pin<&mut WritableDatabase>, err: &mut FfiError);
+        pub(crate) fn replace_document(db: Pin<&mut WritableDatabase>, unique_term: &[u8], doc: Pin<&mut Document>, err: &mut FfiError) -> u32;
+        pub(crate) fn delete_document(db: Pin<&mut WritableDatabase>, unique_term: &[u8], err: &mut FfiError);
+        pub(crate) fn add_document(db: Pin<&mut WritableDatabase>, doc: Pin<&mut Document>, err: &mut FfiError) -> u32;
+        pub(crate) fn replace_document_by_id(db: Pin<&mut WritableDatabase>, did: u32, doc: Pin<&mut Document>, err: &mut FfiError);
+        pub(crate) fn delete_document_by_id(db: Pin<&mut WritableDatabase>, did: u32, err: &mut FfiError);
+        pub(crate) fn begin_transaction(db: Pin<&mut WritableDatabase>, flushed: bool, err: &mut FfiError);
+        pub(crate) fn commit_transaction(db: Pin<&mut WritableDatabase>, err: &mut FfiError);
+        pub(crate) fn cancel_transaction(db: Pin<&mut WritableDatabase>, err: &mut FfiError);
+        pub(crate) fn get_doccount(db: Pin<&mut WritableDatabase>, err: &mut FfiError) -> u32;
+        pub(crate) fn writable_database_get_document(db: &WritableDatabase, did: u32, assume_valid: bool, err: &mut FfiError) -> UniquePtr<Document>;
+        pub(crate) fn writable_database_as_database(db: &WritableDatabase, err: &mut FfiError) -> UniquePtr<Database>;
+
+        pub(crate) fn new_termgenerator(err: &mut FfiError) -> UniquePtr<TermGenerator>;
+        pub(crate) fn set_stemmer(tg: Pin<&mut TermGenerator>, stem: Pin<&mut Stem>, err: &mut FfiError);
+        pub(crate) fn set_document(tg: Pin<&mut TermGenerator>, doc: Pin<&mut Document>, err: &mut FfiError);
+        pub(crate) fn index_text_with_prefix(tg: Pin<&mut TermGenerator>, data: &str, prefix: &str, err: &mut FfiError);
+        pub(crate) fn index_text(tg: Pin<&mut TermGenerator>, data: &str, err: &mut FfiError);
+        pub(crate) fn index_int(tg: Pin<&mut TermGenerator>, data: i32, prefix: &str, err: &mut FfiError);
+        pub(crate) fn index_long(tg: Pin<&mut TermGenerator>, data: i64, prefix: &str, err: &mut FfiError);
+        pub(crate) fn index_float(tg: Pin<&mut TermGenerator>, data: f32, prefix: &str, err: &mut FfiError);
+        pub(crate) fn index_double(tg: Pin<&mut TermGenerator>, data: f64, prefix: &str, err: &mut FfiError);
+
+        pub(crate) fn new_document(err: &mut FfiError) -> UniquePtr<Document>;
+        pub(crate) fn add_string(doc: Pin<&mut Document>, slot: u32, data: &[u8], err: &mut FfiError);
+        pub(crate) fn add_int(doc: Pin<&mut Document>, slot: u32, data: i32, err: &mut FfiError);
+        pub(crate) fn add_long(doc: Pin<&mut Document>, slot: u32, data: i64, err: &mut FfiError);
+        pub(crate) fn add_float(doc: Pin<&mut Document>, slot: u32, data: f32, err: &mut FfiError);
+        pub(crate) fn add_double(doc: Pin<&mut Document>, slot: u32, data: f64, err: &mut FfiError);
+        pub(crate) fn set_data(doc: Pin<&mut Document>, data: &[u8], err: &mut FfiError);
+        pub(crate) fn get_doc_data(doc: &Document, err: &mut FfiError) -> UniquePtr<CxxString>;
+        pub(crate) fn add_boolean_term(doc: Pin<&mut Document>, data: &[u8], err: &mut FfiError);
+        pub(crate) fn add_term(doc: Pin<&mut Document>, term: &[u8], wdf_inc: u32, err: &mut FfiError);
+        pub(crate) fn add_posting(doc: Pin<&mut Document>, term: &[u8], pos: u32, wdf_inc: u32, err: &mut FfiError);
+        pub(crate) fn remove_term(doc: Pin<&mut Document>, term: &[u8], err: &mut FfiError);
+        pub(crate) fn remove_posting(doc: Pin<&mut Document>, term: &[u8], pos: u32, wdf_dec: u32, err: &mut FfiError);
+        pub(crate) fn remove_postings(doc: Pin<&mut Document>, term: &[u8], first: u32, last: u32, wdf_dec: u32, err: &mut FfiError) -> u32;
+        pub(crate) fn clear_terms(doc: Pin<&mut Document>, err: &mut FfiError);
+        pub(crate) fn remove_value(doc: Pin<&mut Document>, slot: u32, err: &mut FfiError);
+        pub(crate) fn clear_values(doc: Pin<&mut Document>, err: &mut FfiError);
+        pub(crate) fn get_value(doc: &Document, slot: u32, err: &mut FfiError) -> UniquePtr<CxxString>;
+        pub(crate) fn get_docid(doc: &Document, err: &mut FfiError) -> u32;
+        pub(crate) fn termlist_count(doc: &Document, err: &mut FfiError) -> u32;
+        pub(crate) fn values_count(doc: &Document, err: &mut FfiError) -> u32;
+        pub(crate) fn document_termlist(doc: &Document, err: &mut FfiError) -> UniquePtr<TermIter>;
+        pub(crate) fn document_values(doc: &Document, err: &mut FfiError) -> UniquePtr<ValueIter>;
+
+        pub(crate) type TermIter;
+        pub(crate) fn term_iter_at_end(iter: Pin<&mut TermIter>) -> bool;
+        pub(crate) fn term_iter_next(iter: Pin<&mut TermIter>, err: &mut FfiError);
+        pub(crate) fn term_iter_term(iter: Pin<&mut TermIter>, err: &mut FfiError) -> UniquePtr<CxxString>;
+        pub(crate) fn term_iter_wdf(iter: Pin<&mut TermIter>, err: &mut FfiError) -> u32;
+        pub(crate) fn term_iter_termfreq(iter: Pin<&mut TermIter>, err: &mut FfiError) -> u32;
+        pub(crate) fn term_iter_positions(iter: Pin<&mut TermIter>, err: &mut FfiError) -> Vec<u32>;
+
+        pub(crate) type ValueIter;
+        pub(crate) fn value_iter_at_end(iter: Pin<&mut ValueIter>) -> bool;
+        pub(crate) fn value_iter_next(iter: Pin<&mut ValueIter>, err: &mut FfiError);
+        pub(crate) fn value_iter_slot(iter: Pin<&mut ValueIter>, err: &mut FfiError) -> u32;
+        pub(crate) fn value_iter_value(iter: Pin<&mut ValueIter>, err: &mut FfiError) -> UniquePtr<CxxString>;
+        pub(crate) fn value_iter_docid(iter: Pin<&mut ValueIter>, err: &mut FfiError) -> u32;
+        pub(crate) fn value_iter_skip_to(iter: Pin<&mut ValueIter>, did: u32, err: &mut FfiError);
+        pub(crate) fn value_iter_check(iter: Pin<&mut ValueIter>, did: u32, err: &mut FfiError) -> bool;
+
+        pub(crate) type PostingIter;
+        pub(crate) fn posting_iter_at_end(iter: Pin<&mut PostingIter>) -> bool;
+        pub(crate) fn posting_iter_next(iter: Pin<&mut PostingIter>, err: &mut FfiError);
+        pub(crate) fn posting_iter_skip_to(iter: Pin<&mut PostingIter>, did: u32, err: &mut FfiError);
+        pub(crate) fn posting_iter_docid(iter: Pin<&mut PostingIter>, err: &mut FfiError) -> u32;
+        pub(crate) fn posting_iter_wdf(iter: Pin<&mut PostingIter>, err: &mut FfiError) -> u32;
+        pub(crate) fn posting_iter_doclength(iter: Pin<&mut PostingIter>, err: &mut FfiError) -> u32;
+
+        pub(crate) type PositionIter;
+        pub(crate) fn position_iter_at_end(iter: Pin<&mut PositionIter>) -> bool;
+        pub(crate) fn position_iter_next(iter: Pin<&mut PositionIter>, err: &mut FfiError);
+        pub(crate) fn position_iter_skip_to(iter: Pin<&mut PositionIter>, pos: u32, err: &mut FfiError);
+        pub(crate) fn position_iter_pos(iter: Pin<&mut PositionIter>, err: &mut FfiError) -> u32;
+
+        pub(crate) fn sortable_serialise_double(value: f64, err: &mut FfiError) -> UniquePtr<CxxString>;
+        pub(crate) fn sortable_unserialise_bytes(serialised: &[u8], err: &mut FfiError) -> f64;
+
+        pub(crate) fn get_matches_estimated(set: &MSet, err: &mut FfiError) -> i32;
+        pub(crate) fn mset_size(set: &MSet, err: &mut FfiError) -> u32;
+        pub(crate) fn get_doc_by_index(set: &MSet, index: u32, err: &mut FfiError) -> UniquePtr<Document>;
+        pub(crate) fn get_matches_lower_bound(set: &MSet, err: &mut FfiError) -> u32;
+        pub(crate) fn get_matches_upper_bound(set: &MSet, err: &mut FfiError) -> u32;
+        pub(crate) fn get_max_possible(set: &MSet, err: &mut FfiError) -> f64;
+        pub(crate) fn get_max_attained(set: &MSet, err: &mut FfiError) -> f64;
+        pub(crate) fn mset_get_termfreq(set: &MSet, term: &[u8], err: &mut FfiError) -> u32;
+        pub(crate) fn mset_get_termweight(set: &MSet, term: &[u8], err: &mut FfiError) -> f64;
+        pub(crate) fn mset_get_hit(set: &MSet, index: u32, err: &mut FfiError) -> MSetHit;
+        pub(crate) fn mset_get_collapse_count(set: &MSet, index: u32, err: &mut FfiError) -> u32;
+        pub(crate) fn mset_get_collapse_key(set: &MSet, index: u32, err: &mut FfiError) -> UniquePtr<CxxString>;
+        pub(crate) fn mset_get_sort_key(set: &MSet, index: u32, err: &mut FfiError) -> UniquePtr<CxxString>;
+
+        pub(crate) fn get_mset(en: Pin<&mut Enquire>, from: i32, size: i32, err: &mut FfiError) -> UniquePtr<MSet>;
+        pub(crate) fn set_query(en: Pin<&mut Enquire>, query: Pin<&mut Query>, err: &mut FfiError);
+        pub(crate) fn set_sort_by_key(en: Pin<&mut Enquire>, sorter: Pin<&mut KeyMaker>, reverse: bool, err: &mut FfiError);
+        pub(crate) fn set_sort_by_key_then_relevance(en: Pin<&mut Enquire>, sorter: Pin<&mut KeyMaker>, reverse: bool, err: &mut FfiError);
+        pub(crate) fn set_sort_by_relevance_then_key(en: Pin<&mut Enquire>, sorter: Pin<&mut KeyMaker>, reverse: bool, err: &mut FfiError);
+        pub(crate) fn set_sort_by_value(en: Pin<&mut Enquire>, slot: u32, reverse: bool, err: &mut FfiError);
+        pub(crate) fn set_sort_by_value_then_relevance(en: Pin<&mut Enquire>, slot: u32, reverse: bool, err: &mut FfiError);
+        pub(crate) fn set_sort_by_relevance_then_value(en: Pin<&mut Enquire>, slot: u32, reverse: bool, err: &mut FfiError);
+        pub(crate) fn set_sort_by_relevance(en: Pin<&mut Enquire>, err: &mut FfiError);
+        pub(crate) fn set_docid_order(en: Pin<&mut Enquire>, order: i32, err: &mut FfiError);
+        pub(crate) fn set_collapse_key(en: Pin<&mut Enquire>, slot: u32, collapse_max: u32, err: &mut FfiError);
+        pub(crate) fn set_weighting_bool(en: Pin<&mut Enquire>, err: &mut FfiError);
+        pub(crate) fn set_weighting_coord(en: Pin<&mut Enquire>, err: &mut FfiError);
+        pub(crate) fn set_weighting_tfidf(en: Pin<&mut Enquire>, normalizations: &str, err: &mut FfiError);
+        pub(crate) fn set_weighting_bm25(en: Pin<&mut Enquire>, k1: f64, k2: f64, k3: f64, b: f64, min_normlen: f64, err: &mut FfiError);
+        #[allow(clippy::too_many_arguments)]
+        pub(crate) fn set_weighting_bm25plus(en: Pin<&mut Enquire>, k1: f64, k2: f64, k3: f64, b: f64, min_normlen: f64, delta: f64, err: &mut FfiError);
+        pub(crate) fn set_weighting_trad(en: Pin<&mut Enquire>, k: f64, err: &mut FfiError);
+        pub(crate) fn set_weighting_inl2(en: Pin<&mut Enquire>, c: f64, err: &mut FfiError);
+        pub(crate) fn set_weighting_ifb2(en: Pin<&mut Enquire>, c: f64, err: &mut FfiError);
+        pub(crate) fn set_weighting_ineb2(en: Pin<&mut Enquire>, c: f64, err: &mut FfiError);
+        pub(crate) fn set_weighting_bb2(en: Pin<&mut Enquire>, c: f64, err: &mut FfiError);
+        pub(crate) fn set_weighting_dlh(en: Pin<&mut Enquire>, err: &mut FfiError);
+        pub(crate) fn set_weighting_pl2(en: Pin<&mut Enquire>, c: f64, err: &mut FfiError);
+        pub(crate) fn set_weighting_pl2plus(en: Pin<&mut Enquire>, c: f64, delta: f64, err: &mut FfiError);
+        pub(crate) fn set_weighting_dph(en: Pin<&mut Enquire>, err: &mut FfiError);
+        pub(crate) fn set_weighting_lm(en: Pin<&mut Enquire>, param_log: f64, smoothing: i32, param_smoothing1: f64, param_smoothing2: f64, err: &mut FfiError);
+        pub(crate) fn set_weighting_rust(en: Pin<&mut Enquire>, weight: Box<WeightBox>, err: &mut FfiError);
+
+        pub(crate) fn new_query_parser(err: &mut FfiError) -> UniquePtr<QueryParser>;
+        pub(crate) fn set_max_wildcard_expansion(qp: Pin<&mut QueryParser>, limit: i32, err: &mut FfiError);
+        pub(crate) fn set_stemmer_to_qp(qp: Pin<&mut QueryParser>, stem: Pin<&mut Stem>, err: &mut FfiError);
+        pub(crate) fn set_database(qp: Pin<&mut QueryParser>, add_db: Pin<&mut Database>, err: &mut FfiError);
+        pub(crate) fn parse_query(qp: Pin<&mut QueryParser>, query_string: &str, flags: i16, err: &mut FfiError) -> UniquePtr<Query>;
+        pub(crate) fn parse_query_with_prefix(qp: Pin<&mut QueryParser>, query_string: &str, flags: i16, prefix: &str, err: &mut FfiError) -> UniquePtr<Query>;
+
+        pub(crate) fn new_query_range(op: i32, slot: u32, begin: f64, end: f64, err: &mut FfiError) -> UniquePtr<Query>;
+        pub(crate) fn add_right_query(this_q: Pin<&mut Query>, op: i32, q: Pin<&mut Query>, err: &mut FfiError) -> UniquePtr<Query>;
+        pub(crate) fn new_query_double_with_prefix(prefix: &str, d: f64, err: &mut FfiError) -> UniquePtr<Query>;
+        pub(crate) fn query_is_empty(this_q: Pin<&mut Query>, err: &mut FfiError) -> bool;
+        pub(crate) fn get_description(this_q: Pin<&mut Query>, err: &mut FfiError) -> UniquePtr<CxxString>;
+
+        pub(crate) fn new_multi_value_key_maker(err: &mut FfiError) -> UniquePtr<MultiValueKeyMaker>;
+        pub(crate) fn add_value_to_multi_value_key_maker(this_m: Pin<&mut MultiValueKeyMaker>, slot: u32, reverse: bool, defvalue: &[u8], err: &mut FfiError);
+        pub(crate) fn multi_value_key_maker_into_key_maker(sorter: UniquePtr<MultiValueKeyMaker>) -> UniquePtr<KeyMaker>;
+        pub(crate) fn new_rust_key_maker(key_fn: Box<KeyMakerBox>, err: &mut FfiError) -> UniquePtr<KeyMaker>;
     }
 }
 
 #[warn(unused_unsafe)]
-
 pub struct MultiValueKeyMaker {
     pub cxxp: UniquePtr<ffi::MultiValueKeyMaker>,
 }
@@ -337,34 +769,86 @@ impl MultiValueKeyMaker {
     pub fn new() -> Result<Self, XError> {
         #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             let obj = ffi::new_multi_value_key_maker(&mut err);
 
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(Self {
                     cxxp: obj,
                 })
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
-    pub fn add_value(&mut self, slot: u32, asc_desc: bool) -> Result<(), XError> {
+    /// Append the value in `slot` to the key, sorting on it in descending order if `reverse`
+    pub fn add_value(&mut self, slot: u32, reverse: bool) -> Result<(), XError> {
+        self.add_value_with_default(slot, reverse, b"")
+    }
+
+    /// Like `add_value`, documents with `slot` unset sort as if they had `defvalue`
+    pub fn add_value_with_default(&mut self, slot: u32, reverse: bool, defvalue: impl AsRef<[u8]>) -> Result<(), XError> {
         #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            ffi::add_value_to_multi_value_key_maker(self.cxxp.pin_mut(), slot, asc_desc, &mut err);
+            let mut err = ffi::FfiError::default();
+            ffi::add_value_to_multi_value_key_maker(self.cxxp.pin_mut(), slot, reverse, defvalue.as_ref(), &mut err);
 
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(())
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
+            }
+        }
+    }
+}
+
+/// Builds the sort key of a document for `Enquire::set_sort_by_key` and related sort orders
+pub struct KeyMaker {
+    cxxp: UniquePtr<ffi::KeyMaker>,
+}
+
+impl KeyMaker {
+    /// Key maker calling `key_fn` for each document to sort, keys compare bytewise.
+    /// `key_fn` is called from C++ during the match, a panic in it aborts the match
+    /// and `Enquire::get_mset` returns it as `XError::Cxx`.
+    pub fn from_fn(key_fn: impl Fn(&Document) -> Vec<u8> + 'static) -> Result<Self, XError> {
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::new_rust_key_maker(Box::new(KeyMakerBox(Box::new(key_fn))), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(Self {
+                    cxxp: obj,
+                })
+            } else {
+                Err(err.into())
             }
         }
     }
 }
 
+impl From<MultiValueKeyMaker> for KeyMaker {
+    fn from(sorter: MultiValueKeyMaker) -> Self {
+        KeyMaker {
+            cxxp: ffi::multi_value_key_maker_into_key_maker(sorter.cxxp),
+        }
+    }
+}
+
+type KeyFn = dyn Fn(&Document) -> Vec<u8>;
+
+pub(crate) struct KeyMakerBox(Box<KeyFn>);
+
+fn key_maker_call(key_maker: &KeyMakerBox, doc: UniquePtr<ffi::Document>) -> Result<Vec<u8>, String> {
+    catch_panic("KeyMaker", || {
+        (key_maker.0)(&Document {
+            cxxp: doc,
+        })
+    })
+}
+
 pub struct Query {
     pub cxxp: UniquePtr<ffi::Query>,
 }
@@ -379,15 +863,15 @@ impl Query {
     pub fn new_range(op: XapianOp, slot: u32, begin: f64, end: f64) -> Result<Self, XError> {
         #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             let obj = ffi::new_query_range(op as i32, slot, begin, end, &mut err);
 
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(Self {
                     cxxp: obj,
                 })
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
@@ -395,15 +879,15 @@ impl Query {
     pub fn add_right(&mut self, op: XapianOp, q: &mut Query) -> Result<Self, XError> {
         #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             let obj = ffi::add_right_query(self.cxxp.pin_mut(), op as i32, q.cxxp.pin_mut(), &mut err);
 
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(Self {
                     cxxp: obj,
                 })
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
@@ -411,15 +895,15 @@ impl Query {
     pub fn new_double_with_prefix(prefix: &str, d: f64) -> Result<Self, XError> {
         #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             let obj = ffi::new_query_double_with_prefix(prefix, d, &mut err);
 
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(Self {
                     cxxp: obj,
                 })
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
@@ -432,9 +916,9 @@ impl Query {
         if !self.cxxp.is_null() {
             #[allow(unused_unsafe)]
             unsafe {
-                let mut err = 0;
+                let mut err = ffi::FfiError::default();
                 let res = ffi::query_is_empty(self.cxxp.pin_mut(), &mut err);
-                if err == 0 {
+                if err.code == ffi::ErrorCode::Ok {
                     return res;
                 } else {
                     return true;
@@ -448,13 +932,11 @@ impl Query {
         if !self.cxxp.is_null() {
             #[allow(unused_unsafe)]
             unsafe {
-                //let mut err = 0;
-                let res = ffi::get_description(self.cxxp.pin_mut());
-                //if err == 0 {
-                return res.to_string();
-                //} else {
-                //    None
-                //}
+                let mut err = ffi::FfiError::default();
+                let res = ffi::get_description(self.cxxp.pin_mut(), &mut err);
+                if err.code == ffi::ErrorCode::Ok {
+                    return res.to_string();
+                }
             }
         }
         String::default()
@@ -469,38 +951,38 @@ pub struct QueryParser {
 impl QueryParser {
     pub fn new() -> Result<Self, XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             let obj = ffi::new_query_parser(&mut err);
 
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(Self {
                     cxxp: obj,
                 })
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
     pub fn set_max_wildcard_expansion(&mut self, limit: i32) -> Result<(), XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             ffi::set_max_wildcard_expansion(self.cxxp.pin_mut(), limit, &mut err);
 
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(())
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
     pub fn set_stemmer(&mut self, stem: &mut Stem) -> Result<(), XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             ffi::set_stemmer_to_qp(self.cxxp.pin_mut(), stem.cxxp.pin_mut(), &mut err);
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
             }
         }
         Ok(())
@@ -508,95 +990,201 @@ impl QueryParser {
 
     pub fn set_database(&mut self, database: &mut Database) -> Result<(), XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             ffi::set_database(self.cxxp.pin_mut(), database.cxxp.pin_mut(), &mut err);
 
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(())
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
     pub fn parse_query(&mut self, query: &str, flags: i16) -> Result<Query, XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             let obj = ffi::parse_query(self.cxxp.pin_mut(), query, flags, &mut err);
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(Query {
                     cxxp: obj,
                 })
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
     pub fn parse_query_with_prefix(&mut self, query: &str, flags: i16, prefix: &str) -> Result<Query, XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             let obj = ffi::parse_query_with_prefix(self.cxxp.pin_mut(), query, flags, prefix, &mut err);
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(Query {
                     cxxp: obj,
                 })
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 }
 
-pub struct MSetIterator<'a> {
-    pub mset: &'a mut MSet,
-    pub index: i32,
+/// One entry of an MSet, the document itself is only fetched on request
+pub struct Hit<'a> {
+    mset: &'a MSet,
+    index: u32,
+    pub docid: DocId,
+    /// Position of the hit in the whole result set, starting from 0
+    pub rank: u32,
+    pub weight: f64,
+    pub percent: i32,
+    /// Fetched on first access to the data or values of the hit
+    document: OnceCell<Document>,
 }
 
-impl<'a> MSetIterator<'a> {
-    pub fn is_next(&mut self) -> Result<bool, XError> {
+impl<'a> Hit<'a> {
+    /// Lower bound on the number of documents collapsed into this hit
+    pub fn get_collapse_count(&self) -> Result<u32, XError> {
         #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            let res = ffi::mset_size(self.mset.cxxp.pin_mut(), &mut err) > self.index;
-
-            if err == 0 {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::mset_get_collapse_count(&self.mset.cxxp, self.index, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(res)
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
-    pub fn next(&mut self) -> Result<(), XError> {
+    /// Value of the collapse slot for this hit, empty if results aren't collapsed
+    pub fn get_collapse_key(&self) -> Result<Vec<u8>, XError> {
         #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            if ffi::mset_size(self.mset.cxxp.pin_mut(), &mut err) > self.index {
-                self.index += 1;
+            let mut err = ffi::FfiError::default();
+            let res = ffi::mset_get_collapse_key(&self.mset.cxxp, self.index, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res.as_bytes().to_vec())
+            } else {
+                Err(err.into())
             }
+        }
+    }
 
-            if err == 0 {
-                Ok(())
+    pub fn get_sort_key(&self) -> Result<Vec<u8>, XError> {
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::mset_get_sort_key(&self.mset.cxxp, self.index, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res.as_bytes().to_vec())
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
-    pub fn get_document_data(&mut self) -> Result<String, XError> {
+    pub fn get_document(&self) -> Result<Document, XError> {
         #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            let mut doc = ffi::get_doc_by_index(self.mset.cxxp.pin_mut(), self.index, &mut err);
-
-            if err == 0 {
-                Ok(ffi::get_doc_data(doc.pin_mut()).to_string())
+            let mut err = ffi::FfiError::default();
+            let doc = ffi::get_doc_by_index(&self.mset.cxxp, self.index, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(Document {
+                    cxxp: doc,
+                })
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
+
+    /// Document of the hit, fetched once and shared by the data and value getters
+    fn document(&self) -> Result<&Document, XError> {
+        if let Some(doc) = self.document.get() {
+            return Ok(doc);
+        }
+        let doc = self.get_document()?;
+        Ok(self.document.get_or_init(|| doc))
+    }
+
+    pub fn get_document_data(&self) -> Result<String, XError> {
+        let data = self.get_document_data_bytes()?;
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+
+    pub fn get_document_data_bytes(&self) -> Result<Vec<u8>, XError> {
+        self.document()?.get_data()
+    }
+
+    pub fn get_value(&self, slot: u32) -> Result<Vec<u8>, XError> {
+        self.document()?.get_value(slot)
+    }
+
+    pub fn get_value_f64(&self, slot: u32) -> Result<Option<f64>, XError> {
+        self.document()?.get_value_f64(slot)
+    }
+
+    pub fn get_value_i64(&self, slot: u32) -> Result<Option<i64>, XError> {
+        self.document()?.get_value_i64(slot)
+    }
+}
+
+/// Iterator over the hits of an MSet in rank order
+pub struct MSetIterator<'a> {
+    mset: &'a MSet,
+    front: u32,
+    back: u32,
+    /// Error reading the size of the MSet, yielded as the only item
+    size_err: Option<XError>,
+}
+
+impl<'a> Iterator for MSetIterator<'a> {
+    type Item = Result<Hit<'a>, XError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.size_err.take() {
+            return Some(Err(err));
+        }
+        if self.front >= self.back {
+            return None;
+        }
+
+        let hit = self.mset.hit(self.front);
+        self.front += 1;
+        Some(hit)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.back - self.front) as usize + self.size_err.is_some() as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a> DoubleEndedIterator for MSetIterator<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.size_err.take() {
+            return Some(Err(err));
+        }
+        if self.front >= self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        Some(self.mset.hit(self.back))
+    }
+}
+
+impl<'a> ExactSizeIterator for MSetIterator<'a> {}
+
+impl<'a> IntoIterator for &'a MSet {
+    type Item = Result<Hit<'a>, XError>;
+    type IntoIter = MSetIterator<'a>;
+
+    fn into_iter(self) -> MSetIterator<'a> {
+        self.iter()
+    }
 }
 
 pub struct MSet {
@@ -604,335 +1192,2314 @@ pub struct MSet {
 }
 
 impl MSet {
-    pub fn iterator(&mut self) -> Result<MSetIterator, i8> {
-        Ok(MSetIterator {
+    pub fn iter(&self) -> MSetIterator<'_> {
+        let (back, size_err) = match self.size() {
+            Ok(size) => (size, None),
+            Err(err) => (0, Some(err)),
+        };
+        MSetIterator {
             mset: self,
-            index: 0,
-        })
+            front: 0,
+            back,
+            size_err,
+        }
     }
 
-    pub fn get_matches_estimated(&mut self) -> Result<i32, XError> {
-        #[allow(unused_unsafe)]
-        unsafe {
-            let mut err = 0;
-            let res = ffi::get_matches_estimated(self.cxxp.pin_mut(), &mut err);
-
-            if err == 0 {
-                Ok(res)
-            } else {
-                Err(XError::Xapian(err))
-            }
+    /// Hit at `index` within this MSet, `None` if out of range
+    pub fn get(&self, index: u32) -> Result<Option<Hit<'_>>, XError> {
+        if index < self.size()? {
+            self.hit(index).map(Some)
+        } else {
+            Ok(None)
         }
     }
-}
 
-pub struct Enquire {
-    pub cxxp: UniquePtr<ffi::Enquire>,
-    sorter: Option<MultiValueKeyMaker>,
-}
+    fn hit(&self, index: u32) -> Result<Hit<'_>, XError> {
+        let mut err = ffi::FfiError::default();
+        let hit = ffi::mset_get_hit(&self.cxxp, index, &mut err);
+        if err.code == ffi::ErrorCode::Ok {
+            Ok(Hit {
+                mset: self,
+                index,
+                docid: DocId(hit.docid),
+                rank: hit.rank,
+                weight: hit.weight,
+                percent: hit.percent,
+                document: OnceCell::new(),
+            })
+        } else {
+            Err(err.into())
+        }
+    }
 
-impl Enquire {
-    pub fn get_mset(&mut self, from: i32, size: i32) -> Result<MSet, XError> {
+    pub fn get_matches_estimated(&self) -> Result<i32, XError> {
         #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            let obj = ffi::get_mset(self.cxxp.pin_mut(), from, size, &mut err);
+            let mut err = ffi::FfiError::default();
+            let res = ffi::get_matches_estimated(&self.cxxp, &mut err);
 
-            if err == 0 {
-                Ok(MSet {
-                    cxxp: obj,
-                })
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
-    pub fn set_query(&mut self, query: &mut Query) -> Result<(), XError> {
+    pub fn get_matches_lower_bound(&self) -> Result<u32, XError> {
         #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            ffi::set_query(self.cxxp.pin_mut(), query.cxxp.pin_mut(), &mut err);
+            let mut err = ffi::FfiError::default();
+            let res = ffi::get_matches_lower_bound(&self.cxxp, &mut err);
 
-            if err == 0 {
-                Ok(())
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
-    pub fn set_sort_by_key(&mut self, mut sorter: MultiValueKeyMaker, reverse: bool) -> Result<(), XError> {
+    pub fn get_matches_upper_bound(&self) -> Result<u32, XError> {
         #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            ffi::set_sort_by_key(self.cxxp.pin_mut(), sorter.cxxp.pin_mut(), reverse, &mut err);
-            self.sorter = Some(sorter);
+            let mut err = ffi::FfiError::default();
+            let res = ffi::get_matches_upper_bound(&self.cxxp, &mut err);
 
-            if err == 0 {
-                Ok(())
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
-}
-
-pub struct Database {
-    pub cxxp: UniquePtr<ffi::Database>,
-}
 
-#[allow(unused_unsafe)]
-impl Database {
-    pub fn new() -> Result<Self, XError> {
+    /// Maximum weight any document could have achieved for the query
+    pub fn get_max_possible(&self) -> Result<f64, XError> {
+        #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            let obj = ffi::new_database(&mut err);
+            let mut err = ffi::FfiError::default();
+            let res = ffi::get_max_possible(&self.cxxp, &mut err);
 
-            if err == 0 {
-                Ok(Self {
-                    cxxp: obj,
-                })
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
-    pub fn new_with_path(path: &str, db_type: i8) -> Result<Self, XError> {
+    /// Greatest weight attained by any document
+    pub fn get_max_attained(&self) -> Result<f64, XError> {
+        #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            let obj = ffi::new_database_with_path(path, db_type, &mut err);
+            let mut err = ffi::FfiError::default();
+            let res = ffi::get_max_attained(&self.cxxp, &mut err);
 
-            if err == 0 {
-                Ok(Self {
-                    cxxp: obj,
-                })
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
-    pub fn new_enquire(&mut self) -> Result<Enquire, XError> {
+    /// Number of hits in this MSet
+    pub fn size(&self) -> Result<u32, XError> {
+        #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            let obj = ffi::new_enquire(self.cxxp.pin_mut(), &mut err);
+            let mut err = ffi::FfiError::default();
+            let res = ffi::mset_size(&self.cxxp, &mut err);
 
-            if err == 0 {
-                Ok(Enquire {
-                    cxxp: obj,
-                    sorter: None,
-                })
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
-    pub fn add_database(&mut self, database: &mut Database) -> Result<(), XError> {
+    pub fn get_termfreq(&self, term: impl AsRef<[u8]>) -> Result<u32, XError> {
+        #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            ffi::add_database(self.cxxp.pin_mut(), database.cxxp.pin_mut(), &mut err);
+            let mut err = ffi::FfiError::default();
+            let res = ffi::mset_get_termfreq(&self.cxxp, term.as_ref(), &mut err);
 
-            if err == 0 {
-                Ok(())
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
 
-    pub fn reopen(&mut self) -> Result<(), XError> {
+    pub fn get_termweight(&self, term: impl AsRef<[u8]>) -> Result<f64, XError> {
+        #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            ffi::database_reopen(self.cxxp.pin_mut(), &mut err);
+            let mut err = ffi::FfiError::default();
+            let res = ffi::mset_get_termweight(&self.cxxp, term.as_ref(), &mut err);
 
-            if err == 0 {
-                Ok(())
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
+}
 
-    pub fn close(&mut self) -> Result<(), XError> {
-        unsafe {
-            let mut err = 0;
-            ffi::database_close(self.cxxp.pin_mut(), &mut err);
+/// Smoothing used by the `LM` weighting scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LMSmoothing {
+    TwoStage = 1,
+    Dirichlet = 2,
+    AbsoluteDiscount = 3,
+    JelinekMercer = 4,
+    DirichletPlus = 5,
+}
 
-            if err == 0 {
-                Ok(())
-            } else {
-                Err(XError::Xapian(err))
-            }
+impl LMSmoothing {
+    fn from_param(param: &str) -> Option<LMSmoothing> {
+        match param {
+            "1" | "two_stage" => Some(LMSmoothing::TwoStage),
+            "2" | "dirichlet" => Some(LMSmoothing::Dirichlet),
+            "3" | "absolute_discount" => Some(LMSmoothing::AbsoluteDiscount),
+            "4" | "jelinek_mercer" => Some(LMSmoothing::JelinekMercer),
+            "5" | "dirichlet_plus" => Some(LMSmoothing::DirichletPlus),
+            _ => None,
         }
     }
 }
 
-pub struct WritableDatabase {
-    cxxp: UniquePtr<ffi::WritableDatabase>,
+/// Weighting schemes of Xapian, parameters and defaults are those of the `Xapian::Weight` subclasses.
+///
+/// Parses from and displays as the string form used by omega, the scheme name followed by
+/// its parameters in constructor order, e.g. `"bm25 1.2 0 1 0.75 0.5"`.
+/// Trailing parameters may be left out to use their defaults.
+/// Every scheme but `Custom` parses back from its displayed form, `Custom` displays as
+/// the `Weight::name` of its weight and has no string form.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WeightingScheme {
+    /// Every match gets weight 0
+    Bool,
+    /// Weight is the number of matching query terms
+    Coord,
+    /// `normalizations` are the three letter SMART codes, default `"ntn"`
+    TfIdf {
+        normalizations: String,
+    },
+    /// Defaults `1 0 1 0.5 0.5`
+    BM25 {
+        k1: f64,
+        k2: f64,
+        k3: f64,
+        b: f64,
+        min_normlen: f64,
+    },
+    /// Defaults `1 0 1 0.5 0.5 1`
+    BM25Plus {
+        k1: f64,
+        k2: f64,
+        k3: f64,
+        b: f64,
+        min_normlen: f64,
+        delta: f64,
+    },
+    /// Default `1`
+    Trad {
+        k: f64,
+    },
+    /// Default `1`
+    InL2 {
+        c: f64,
+    },
+    /// Default `1`
+    IfB2 {
+        c: f64,
+    },
+    /// Default `1`
+    IneB2 {
+        c: f64,
+    },
+    /// Default `1`
+    BB2 {
+        c: f64,
+    },
+    DLH,
+    /// Default `1`
+    PL2 {
+        c: f64,
+    },
+    /// Defaults `1 0.8`
+    PL2Plus {
+        c: f64,
+        delta: f64,
+    },
+    DPH,
+    /// Defaults `0 1 0.7 2000`, or `0.05` for `param_smoothing2` with a smoothing other than `TwoStage`.
+    /// A `param_log` of 0 uses the document length upper bound.
+    LM {
+        param_log: f64,
+        smoothing: LMSmoothing,
+        param_smoothing1: f64,
+        param_smoothing2: f64,
+    },
+    /// Scheme implemented in Rust, has no string form to parse
+    Custom(CustomWeight),
 }
 
-#[allow(unused_unsafe)]
-impl WritableDatabase {
-    pub fn new(path: &str, action: i8, db_type: i8) -> Result<Self, XError> {
-        unsafe {
-            let mut err = 0;
-            let obj = ffi::new_writable_database_with_path(path, action, db_type, &mut err);
+impl WeightingScheme {
+    pub fn custom(weight: impl Weight + 'static) -> Self {
+        WeightingScheme::Custom(CustomWeight::new(weight))
+    }
+}
 
-            if err == 0 {
-                Ok(Self {
-                    cxxp: obj,
-                })
-            } else {
-                Err(XError::Xapian(err))
-            }
+impl Default for WeightingScheme {
+    fn default() -> Self {
+        WeightingScheme::BM25 {
+            k1: 1.0,
+            k2: 0.0,
+            k3: 1.0,
+            b: 0.5,
+            min_normlen: 0.5,
         }
     }
+}
 
-    pub fn delete_document(&mut self, unique_term: &str) -> Result<(), XError> {
-        unsafe {
-            let mut err = 0;
-            ffi::delete_document(self.cxxp.pin_mut(), unique_term, &mut err);
-            if err < 0 {
-                return Err(XError::Xapian(err));
+impl FromStr for WeightingScheme {
+    type Err = ParseWeightingSchemeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut params = s.split_whitespace();
+        let name = params.next().ok_or_else(|| ParseWeightingSchemeError("empty weighting scheme".to_string()))?;
+        let mut param = |default: f64| next_weighting_param(&mut params, name, default);
+
+        let scheme = match name {
+            "bool" => WeightingScheme::Bool,
+            "coord" => WeightingScheme::Coord,
+            "tfidf" => WeightingScheme::TfIdf {
+                normalizations: params.next().unwrap_or("ntn").to_string(),
+            },
+            "bm25" => WeightingScheme::BM25 {
+                k1: param(1.0)?,
+                k2: param(0.0)?,
+                k3: param(1.0)?,
+                b: param(0.5)?,
+                min_normlen: param(0.5)?,
+            },
+            "bm25+" => WeightingScheme::BM25Plus {
+                k1: param(1.0)?,
+                k2: param(0.0)?,
+                k3: param(1.0)?,
+                b: param(0.5)?,
+                min_normlen: param(0.5)?,
+                delta: param(1.0)?,
+            },
+            "trad" => WeightingScheme::Trad {
+                k: param(1.0)?,
+            },
+            "inl2" => WeightingScheme::InL2 {
+                c: param(1.0)?,
+            },
+            "ifb2" => WeightingScheme::IfB2 {
+                c: param(1.0)?,
+            },
+            "ineb2" => WeightingScheme::IneB2 {
+                c: param(1.0)?,
+            },
+            "bb2" => WeightingScheme::BB2 {
+                c: param(1.0)?,
+            },
+            "dlh" => WeightingScheme::DLH,
+            "pl2" => WeightingScheme::PL2 {
+                c: param(1.0)?,
+            },
+            "pl2+" => WeightingScheme::PL2Plus {
+                c: param(1.0)?,
+                delta: param(0.8)?,
+            },
+            "dph" => WeightingScheme::DPH,
+            "lm" => {
+                let param_log = next_weighting_param(&mut params, name, 0.0)?;
+                let smoothing = match params.next() {
+                    None => LMSmoothing::TwoStage,
+                    Some(p) => LMSmoothing::from_param(p).ok_or_else(|| ParseWeightingSchemeError(format!("invalid smoothing {:?} for lm", p)))?,
+                };
+                WeightingScheme::LM {
+                    param_log,
+                    smoothing,
+                    param_smoothing1: next_weighting_param(&mut params, name, 0.7)?,
+                    param_smoothing2: next_weighting_param(&mut params, name, if smoothing == LMSmoothing::TwoStage {
+                        2000.0
+                    } else {
+                        0.05
+                    })?,
+                }
             }
+            _ => return Err(ParseWeightingSchemeError(format!("unknown weighting scheme {:?}", name))),
+        };
+
+        if let Some(extra) = params.next() {
+            return Err(ParseWeightingSchemeError(format!("too many parameters for {}, starting at {:?}", name, extra)));
         }
-        Ok(())
+        Ok(scheme)
     }
+}
 
-    pub fn replace_document(&mut self, unique_term: &str, doc: &mut Document) -> Result<(), XError> {
-        unsafe {
-            let mut err = 0;
-            ffi::replace_document(self.cxxp.pin_mut(), unique_term, doc.cxxp.pin_mut(), &mut err);
-            if err < 0 {
-                return Err(XError::Xapian(err));
-            }
+fn next_weighting_param(params: &mut SplitWhitespace, name: &str, default: f64) -> Result<f64, ParseWeightingSchemeError> {
+    match params.next() {
+        None => Ok(default),
+        Some(p) => p.parse().map_err(|_| ParseWeightingSchemeError(format!("invalid parameter {:?} for {}", p, name))),
+    }
+}
+
+impl Display for WeightingScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeightingScheme::Bool => write!(f, "bool"),
+            WeightingScheme::Coord => write!(f, "coord"),
+            WeightingScheme::TfIdf {
+                normalizations,
+            } => write!(f, "tfidf {}", normalizations),
+            WeightingScheme::BM25 {
+                k1,
+                k2,
+                k3,
+                b,
+                min_normlen,
+            } => write!(f, "bm25 {} {} {} {} {}", k1, k2, k3, b, min_normlen),
+            WeightingScheme::BM25Plus {
+                k1,
+                k2,
+                k3,
+                b,
+                min_normlen,
+                delta,
+            } => write!(f, "bm25+ {} {} {} {} {} {}", k1, k2, k3, b, min_normlen, delta),
+            WeightingScheme::Trad {
+                k,
+            } => write!(f, "trad {}", k),
+            WeightingScheme::InL2 {
+                c,
+            } => write!(f, "inl2 {}", c),
+            WeightingScheme::IfB2 {
+                c,
+            } => write!(f, "ifb2 {}", c),
+            WeightingScheme::IneB2 {
+                c,
+            } => write!(f, "ineb2 {}", c),
+            WeightingScheme::BB2 {
+                c,
+            } => write!(f, "bb2 {}", c),
+            WeightingScheme::DLH => write!(f, "dlh"),
+            WeightingScheme::PL2 {
+                c,
+            } => write!(f, "pl2 {}", c),
+            WeightingScheme::PL2Plus {
+                c,
+                delta,
+            } => write!(f, "pl2+ {} {}", c, delta),
+            WeightingScheme::DPH => write!(f, "dph"),
+            WeightingScheme::LM {
+                param_log,
+                smoothing,
+                param_smoothing1,
+                param_smoothing2,
+            } => write!(f, "lm {} {} {} {}", param_log, *smoothing as i32, param_smoothing1, param_smoothing2),
+            WeightingScheme::Custom(weight) => f.write_str(&weight.0.name()),
         }
-        Ok(())
     }
+}
 
-    pub fn commit(&mut self) -> Result<(), XError> {
+/// Statistics a custom `Weight` asks for, the `Xapian::Weight::stat_flags`.
+///
+/// Combine them with `|`, e.g. `StatFlags::WDF | StatFlags::TERMFREQ`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct StatFlags(u32);
+
+impl StatFlags {
+    /// Number of documents in the collection
+    pub const COLLECTION_SIZE: StatFlags = StatFlags(1);
+    /// Number of documents in the relevance set
+    pub const RSET_SIZE: StatFlags = StatFlags(2);
+    /// Average length of documents in the collection
+    pub const AVERAGE_LENGTH: StatFlags = StatFlags(4);
+    /// Number of documents the current term is in
+    pub const TERMFREQ: StatFlags = StatFlags(8);
+    /// Number of documents in the relevance set the current term is in
+    pub const RELTERMFREQ: StatFlags = StatFlags(16);
+    /// Sum of wqf for the terms in the query
+    pub const QUERY_LENGTH: StatFlags = StatFlags(32);
+    /// Within-query frequency of the current term
+    pub const WQF: StatFlags = StatFlags(64);
+    /// `wdf` passed to `get_sumpart`
+    pub const WDF: StatFlags = StatFlags(128);
+    /// `doclen` passed to `get_sumpart` and `get_sumextra`
+    pub const DOC_LENGTH: StatFlags = StatFlags(256);
+    /// Lower bound on non-zero document lengths
+    pub const DOC_LENGTH_MIN: StatFlags = StatFlags(512);
+    /// Upper bound on document lengths
+    pub const DOC_LENGTH_MAX: StatFlags = StatFlags(1024);
+    /// Upper bound on the wdf of the current term
+    pub const WDF_MAX: StatFlags = StatFlags(2048);
+    /// Sum of the wdf of the current term over the collection
+    pub const COLLECTION_FREQ: StatFlags = StatFlags(4096);
+    /// `uniqterms` passed to `get_sumpart` and `get_sumextra`
+    pub const UNIQUE_TERMS: StatFlags = StatFlags(8192);
+    /// Sum of document lengths in the collection
+    pub const TOTAL_LENGTH: StatFlags = StatFlags(1 | 4);
+
+    pub const fn empty() -> Self {
+        StatFlags(0)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn contains(self, other: StatFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for StatFlags {
+    type Output = StatFlags;
+
+    fn bitor(self, rhs: StatFlags) -> StatFlags {
+        StatFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for StatFlags {
+    fn bitor_assign(&mut self, rhs: StatFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Statistics for a custom `Weight`, only those asked for with `Weight::stats_needed` are set,
+/// the others are 0. Collection and relevance set sizes are always set.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct WeightStats {
+    pub collection_size: u32,
+    pub rset_size: u32,
+    pub average_length: f64,
+    pub termfreq: u32,
+    pub reltermfreq: u32,
+    pub collection_freq: u32,
+    pub query_length: u32,
+    pub wqf: u32,
+    pub doclength_lower_bound: u32,
+    pub doclength_upper_bound: u32,
+    pub wdf_upper_bound: u32,
+    pub total_length: u64,
+}
+
+impl From<&ffi::FfiWeightStats> for WeightStats {
+    fn from(stats: &ffi::FfiWeightStats) -> Self {
+        WeightStats {
+            collection_size: stats.collection_size,
+            rset_size: stats.rset_size,
+            average_length: stats.average_length,
+            termfreq: stats.termfreq,
+            reltermfreq: stats.reltermfreq,
+            collection_freq: stats.collection_freq,
+            query_length: stats.query_length,
+            wqf: stats.wqf,
+            doclength_lower_bound: stats.doclength_lower_bound,
+            doclength_upper_bound: stats.doclength_upper_bound,
+            wdf_upper_bound: stats.wdf_upper_bound,
+            total_length: stats.total_length,
+        }
+    }
+}
+
+/// Weighting scheme implemented in Rust, the counterpart of a `Xapian::Weight` subclass.
+///
+/// The matcher clones the installed weight for each query term and calls `init` on the clone,
+/// plus once more with `factor` 0 for the term-independent part from `get_sumextra`.
+/// The methods are called from C++ during the match, a panic in one of them aborts the match
+/// and `Enquire::get_mset` returns it as `XError::Cxx`.
+pub trait Weight: Send + Sync {
+    /// Copy of the weight in its configured state
+    fn clone_box(&self) -> Box<dyn Weight>;
+
+    /// Statistics to collect for `init`, and arguments to pass to `get_sumpart`/`get_sumextra`,
+    /// those not asked for are 0
+    fn stats_needed(&self) -> StatFlags;
+
+    /// Prepare for a term, `factor` scales its weight, e.g. from `OP_SCALE_WEIGHT`
+    fn init(&mut self, factor: f64, stats: &WeightStats);
+
+    /// Weight the term contributes to a document
+    fn get_sumpart(&self, wdf: u32, doclen: u32, uniqterms: u32) -> f64;
+
+    /// Upper bound on `get_sumpart` for any document, the tighter the faster the match
+    fn get_maxpart(&self) -> f64;
+
+    /// Term-independent weight of a document
+    fn get_sumextra(&self, _doclen: u32, _uniqterms: u32) -> f64 {
+        0.0
+    }
+
+    /// Upper bound on `get_sumextra` for any document
+    fn get_maxextra(&self) -> f64 {
+        0.0
+    }
+
+    /// Shown when the `WeightingScheme` is displayed
+    fn name(&self) -> String {
+        "custom".to_string()
+    }
+}
+
+/// Shared handle on a custom `Weight`, each `Enquire` it is installed on gets its own clone.
+/// Handles compare equal if they refer to the same weight.
+#[derive(Clone)]
+pub struct CustomWeight(Arc<dyn Weight>);
+
+impl CustomWeight {
+    pub fn new(weight: impl Weight + 'static) -> Self {
+        CustomWeight(Arc::new(weight))
+    }
+}
+
+impl fmt::Debug for CustomWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CustomWeight").field(&self.0.name()).finish()
+    }
+}
+
+impl PartialEq for CustomWeight {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+pub(crate) struct WeightBox(Box<dyn Weight>);
+
+fn weight_clone(weight: &WeightBox) -> Result<Box<WeightBox>, String> {
+    catch_panic("Weight::clone_box", || Box::new(WeightBox(weight.0.clone_box())))
+}
+
+fn weight_stats_needed(weight: &WeightBox) -> Result<u32, String> {
+    catch_panic("Weight::stats_needed", || weight.0.stats_needed().bits())
+}
+
+fn weight_init(weight: &mut WeightBox, factor: f64, stats: &ffi::FfiWeightStats) -> Result<(), String> {
+    catch_panic("Weight::init", || weight.0.init(factor, &stats.into()))
+}
+
+fn weight_get_sumpart(weight: &WeightBox, wdf: u32, doclen: u32, uniqterms: u32) -> Result<f64, String> {
+    catch_panic("Weight::get_sumpart", || weight.0.get_sumpart(wdf, doclen, uniqterms))
+}
+
+fn weight_get_maxpart(weight: &WeightBox) -> Result<f64, String> {
+    catch_panic("Weight::get_maxpart", || weight.0.get_maxpart())
+}
+
+fn weight_get_sumextra(weight: &WeightBox, doclen: u32, uniqterms: u32) -> Result<f64, String> {
+    catch_panic("Weight::get_sumextra", || weight.0.get_sumextra(doclen, uniqterms))
+}
+
+fn weight_get_maxextra(weight: &WeightBox) -> Result<f64, String> {
+    catch_panic("Weight::get_maxextra", || weight.0.get_maxextra())
+}
+
+// Unwinding into C++ is undefined behaviour, callbacks from C++ turn a panic into an error
+// which cxx throws as `rust::Error` and `set_error` reports as `CxxException`
+fn catch_panic<T>(callback: &str, f: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let msg = if let Some(msg) = payload.downcast_ref::<&str>() {
+            msg
+        } else if let Some(msg) = payload.downcast_ref::<String>() {
+            msg.as_str()
+        } else {
+            "non-string payload"
+        };
+        format!("{} panicked: {}", callback, msg)
+    })
+}
+
+/// Error parsing the string form of a `WeightingScheme`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWeightingSchemeError(String);
+
+impl Display for ParseWeightingSchemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl StdError for ParseWeightingSchemeError {}
+
+/// Order of documents which sort equal, `Xapian::Enquire::docid_order`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DocidOrder {
+    #[default]
+    Ascending = 1,
+    Descending = 0,
+    /// Whichever order is fastest, can differ between databases
+    DontCare = 2,
+}
+
+pub struct Enquire {
+    pub cxxp: UniquePtr<ffi::Enquire>,
+    /// The C++ Enquire only keeps a pointer to its key maker, which may outlive
+    /// a switch to another sort order, so it's kept until replaced
+    sorter: Option<KeyMaker>,
+}
+
+impl Enquire {
+    pub fn get_mset(&mut self, from: i32, size: i32) -> Result<MSet, XError> {
+        #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            ffi::commit(self.cxxp.pin_mut(), &mut err);
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::get_mset(self.cxxp.pin_mut(), from, size, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(MSet {
+                    cxxp: obj,
+                })
+            } else {
+                Err(err.into())
             }
         }
-        Ok(())
     }
 
-    pub fn get_doccount(&mut self) -> Result<i32, XError> {
+    pub fn set_query(&mut self, query: &mut Query) -> Result<(), XError> {
+        #[allow(unused_unsafe)]
         unsafe {
-            let mut err = 0;
-            let res = ffi::get_doccount(self.cxxp.pin_mut(), &mut err);
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            let mut err = ffi::FfiError::default();
+            ffi::set_query(self.cxxp.pin_mut(), query.cxxp.pin_mut(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
             } else {
-                Ok(res)
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Rank matches with `scheme` instead of the default BM25
+    pub fn set_weighting_scheme(&mut self, scheme: &WeightingScheme) -> Result<(), XError> {
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let en = self.cxxp.pin_mut();
+            match *scheme {
+                WeightingScheme::Bool => ffi::set_weighting_bool(en, &mut err),
+                WeightingScheme::Coord => ffi::set_weighting_coord(en, &mut err),
+                WeightingScheme::TfIdf {
+                    ref normalizations,
+                } => ffi::set_weighting_tfidf(en, normalizations, &mut err),
+                WeightingScheme::BM25 {
+                    k1,
+                    k2,
+                    k3,
+                    b,
+                    min_normlen,
+                } => ffi::set_weighting_bm25(en, k1, k2, k3, b, min_normlen, &mut err),
+                WeightingScheme::BM25Plus {
+                    k1,
+                    k2,
+                    k3,
+                    b,
+                    min_normlen,
+                    delta,
+                } => ffi::set_weighting_bm25plus(en, k1, k2, k3, b, min_normlen, delta, &mut err),
+                WeightingScheme::Trad {
+                    k,
+                } => ffi::set_weighting_trad(en, k, &mut err),
+                WeightingScheme::InL2 {
+                    c,
+                } => ffi::set_weighting_inl2(en, c, &mut err),
+                WeightingScheme::IfB2 {
+                    c,
+                } => ffi::set_weighting_ifb2(en, c, &mut err),
+                WeightingScheme::IneB2 {
+                    c,
+                } => ffi::set_weighting_ineb2(en, c, &mut err),
+                WeightingScheme::BB2 {
+                    c,
+                } => ffi::set_weighting_bb2(en, c, &mut err),
+                WeightingScheme::DLH => ffi::set_weighting_dlh(en, &mut err),
+                WeightingScheme::PL2 {
+                    c,
+                } => ffi::set_weighting_pl2(en, c, &mut err),
+                WeightingScheme::PL2Plus {
+                    c,
+                    delta,
+                } => ffi::set_weighting_pl2plus(en, c, delta, &mut err),
+                WeightingScheme::DPH => ffi::set_weighting_dph(en, &mut err),
+                WeightingScheme::LM {
+                    param_log,
+                    smoothing,
+                    param_smoothing1,
+                    param_smoothing2,
+                } => ffi::set_weighting_lm(en, param_log, smoothing as i32, param_smoothing1, param_smoothing2, &mut err),
+                WeightingScheme::Custom(ref weight) => ffi::set_weighting_rust(en, Box::new(WeightBox(weight.0.clone_box())), &mut err),
+            }
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn set_sort_by_key(&mut self, sorter: impl Into<KeyMaker>, reverse: bool) -> Result<(), XError> {
+        let mut sorter = sorter.into();
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::set_sort_by_key(self.cxxp.pin_mut(), sorter.cxxp.pin_mut(), reverse, &mut err);
+            self.sorter = Some(sorter);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Sort by the key `sorter` builds, ties are ranked by relevance
+    pub fn set_sort_by_key_then_relevance(&mut self, sorter: impl Into<KeyMaker>, reverse: bool) -> Result<(), XError> {
+        let mut sorter = sorter.into();
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::set_sort_by_key_then_relevance(self.cxxp.pin_mut(), sorter.cxxp.pin_mut(), reverse, &mut err);
+            self.sorter = Some(sorter);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Rank by relevance, ties are sorted by the key `sorter` builds
+    pub fn set_sort_by_relevance_then_key(&mut self, sorter: impl Into<KeyMaker>, reverse: bool) -> Result<(), XError> {
+        let mut sorter = sorter.into();
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::set_sort_by_relevance_then_key(self.cxxp.pin_mut(), sorter.cxxp.pin_mut(), reverse, &mut err);
+            self.sorter = Some(sorter);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Sort by the value in `slot`, compared bytewise, ascending unless `reverse`
+    pub fn set_sort_by_value(&mut self, slot: u32, reverse: bool) -> Result<(), XError> {
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::set_sort_by_value(self.cxxp.pin_mut(), slot, reverse, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Sort by the value in `slot`, ties are ranked by relevance
+    pub fn set_sort_by_value_then_relevance(&mut self, slot: u32, reverse: bool) -> Result<(), XError> {
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::set_sort_by_value_then_relevance(self.cxxp.pin_mut(), slot, reverse, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Rank by relevance, ties are sorted by the value in `slot`
+    pub fn set_sort_by_relevance_then_value(&mut self, slot: u32, reverse: bool) -> Result<(), XError> {
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::set_sort_by_relevance_then_value(self.cxxp.pin_mut(), slot, reverse, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Rank by relevance only, the default
+    pub fn set_sort_by_relevance(&mut self) -> Result<(), XError> {
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::set_sort_by_relevance(self.cxxp.pin_mut(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Keep at most `collapse_max` matches with the same value in `slot`, the best ranked ones.
+    /// `Hit::get_collapse_count` gives a lower bound on how many were dropped for a hit,
+    /// matches with `slot` unset are never collapsed.
+    pub fn set_collapse_key(&mut self, slot: u32, collapse_max: u32) -> Result<(), XError> {
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::set_collapse_key(self.cxxp.pin_mut(), slot, collapse_max, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Turn collapsing off again
+    pub fn clear_collapse_key(&mut self) -> Result<(), XError> {
+        // Xapian::BAD_VALUENO
+        self.set_collapse_key(u32::MAX, 1)
+    }
+
+    /// Order of documents which sort equal, ascending docid by default
+    pub fn set_docid_order(&mut self, order: DocidOrder) -> Result<(), XError> {
+        #[allow(unused_unsafe)]
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::set_docid_order(self.cxxp.pin_mut(), order as i32, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
             }
         }
     }
 }
 
-pub struct Document {
-    cxxp: UniquePtr<ffi::Document>,
+pub struct Database {
+    pub cxxp: UniquePtr<ffi::Database>,
+    backend: Option<Backend>,
 }
 
 #[allow(unused_unsafe)]
-impl Document {
+impl Database {
     pub fn new() -> Result<Self, XError> {
         unsafe {
-            let mut err = 0;
-            let obj = ffi::new_document(&mut err);
-            if err == 0 {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::new_database(&mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(Self {
+                    cxxp: obj,
+                    backend: None,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn new_with_path(path: &str, backend: Backend) -> Result<Self, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let flags = backend.flags();
+            let obj = ffi::new_database_with_path(path, flags.bits(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(Self {
                     cxxp: obj,
+                    backend: Backend::in_use(path, flags),
                 })
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Backend of the opened database, `None` if it isn't a single known database
+    pub fn get_backend(&self) -> Option<Backend> {
+        self.backend
+    }
+
+    /// Number of documents in the database
+    pub fn get_doccount(&self) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_doccount(&self.cxxp, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
             }
         }
-    }
+    }
+
+    /// Highest document id which has been used, `None` if no document was ever added
+    pub fn get_lastdocid(&self) -> Result<Option<DocId>, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_lastdocid(&self.cxxp, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(DocId::new(res))
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Average length of the documents
+    pub fn get_avlength(&self) -> Result<f64, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_avlength(&self.cxxp, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Sum of the lengths of all documents
+    pub fn get_total_length(&self) -> Result<u64, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_total_length(&self.cxxp, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Length of a document, the sum of its wdfs
+    pub fn get_doclength(&self, did: DocId) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_doclength(&self.cxxp, did.0, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Number of distinct terms in a document
+    pub fn get_unique_terms(&self, did: DocId) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_unique_terms(&self.cxxp, did.0, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Number of documents indexed by a term
+    pub fn get_termfreq(&self, term: impl AsRef<[u8]>) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_termfreq(&self.cxxp, term.as_ref(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Total number of occurrences of a term
+    pub fn get_collection_freq(&self, term: impl AsRef<[u8]>) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_collection_freq(&self.cxxp, term.as_ref(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn term_exists(&self, term: impl AsRef<[u8]>) -> Result<bool, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_term_exists(&self.cxxp, term.as_ref(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Lower bound on the length of any document, ignoring empty ones
+    pub fn get_doclength_lower_bound(&self) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_doclength_lower_bound(&self.cxxp, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Upper bound on the length of any document
+    pub fn get_doclength_upper_bound(&self) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_doclength_upper_bound(&self.cxxp, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Upper bound on the wdf of a term in any document
+    pub fn get_wdf_upper_bound(&self, term: impl AsRef<[u8]>) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_wdf_upper_bound(&self.cxxp, term.as_ref(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Terms in the database starting with `prefix`, in sorted order, with their termfreq.
+    /// An empty prefix lists every term.
+    pub fn allterms(&self, prefix: impl AsRef<[u8]>) -> Result<AllTermsIterator<'_>, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::database_allterms(&self.cxxp, prefix.as_ref(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(AllTermsIterator {
+                    db: self,
+                    cxxp: obj,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Documents indexed by `term` in ascending docid order, with wdf and document length
+    pub fn postlist(&self, term: impl AsRef<[u8]>) -> Result<PostingIterator, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::database_postlist(&self.cxxp, term.as_ref(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(PostingIterator {
+                    cxxp: obj,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Positions of `term` in document `did`, in ascending order
+    pub fn positionlist(&self, did: DocId, term: impl AsRef<[u8]>) -> Result<PositionIterator, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::database_positionlist(&self.cxxp, did.0, term.as_ref(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(PositionIterator {
+                    cxxp: obj,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Document with id `did`, fails with `XapianErrorKind::DocNotFoundError` if there is none
+    pub fn get_document(&self, did: DocId) -> Result<Document, XError> {
+        self.get_document_with_flags(did, false)
+    }
+
+    /// Document with id `did` opened with `DOC_ASSUME_VALID`, its contents are loaded lazily
+    /// and a missing document may only be reported once they are read.
+    pub fn get_document_lazy(&self, did: DocId) -> Result<Document, XError> {
+        self.get_document_with_flags(did, true)
+    }
+
+    fn get_document_with_flags(&self, did: DocId, assume_valid: bool) -> Result<Document, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let doc = ffi::database_get_document(&self.cxxp, did.0, assume_valid, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(Document {
+                    cxxp: doc,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Values stored in `slot` in ascending docid order, yields `(docid, value)`
+    pub fn valuestream(&self, slot: u32) -> Result<ValueStreamIterator, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::database_valuestream(&self.cxxp, slot, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(ValueStreamIterator {
+                    cxxp: obj,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Number of documents with a value in `slot`
+    pub fn get_value_freq(&self, slot: u32) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_value_freq(&self.cxxp, slot, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Lower bound on the values stored in `slot`, compared bytewise.
+    /// Slots written with `add_double` decode with `sortable_unserialise`.
+    pub fn get_value_lower_bound(&self, slot: u32) -> Result<Vec<u8>, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_value_lower_bound(&self.cxxp, slot, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res.as_bytes().to_vec())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Upper bound on the values stored in `slot`, compared bytewise
+    pub fn get_value_upper_bound(&self, slot: u32) -> Result<Vec<u8>, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::database_get_value_upper_bound(&self.cxxp, slot, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res.as_bytes().to_vec())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn new_enquire(&mut self) -> Result<Enquire, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::new_enquire(self.cxxp.pin_mut(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(Enquire {
+                    cxxp: obj,
+                    sorter: None,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn add_database(&mut self, database: &mut Database) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::add_database(self.cxxp.pin_mut(), database.cxxp.pin_mut(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                // a combined database has no single backend
+                self.backend = None;
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn reopen(&mut self) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::database_reopen(self.cxxp.pin_mut(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn close(&mut self) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::database_close(self.cxxp.pin_mut(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+}
+
+/// Transaction on a `WritableDatabase`, cancelled on drop unless committed
+pub struct Transaction<'a> {
+    db: &'a mut WritableDatabase,
+    finished: bool,
+}
+
+#[allow(unused_unsafe)]
+impl<'a> Transaction<'a> {
+    pub fn commit(mut self) -> Result<(), XError> {
+        self.finished = true;
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::commit_transaction(self.db.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Discard the changes made in the transaction
+    pub fn cancel(mut self) -> Result<(), XError> {
+        self.finished = true;
+        self.cancel_inner()
+    }
+
+    fn cancel_inner(&mut self) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::cancel_transaction(self.db.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Deref for Transaction<'a> {
+    type Target = WritableDatabase;
+
+    fn deref(&self) -> &WritableDatabase {
+        self.db
+    }
+}
+
+impl<'a> DerefMut for Transaction<'a> {
+    fn deref_mut(&mut self) -> &mut WritableDatabase {
+        self.db
+    }
+}
+
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        if !self.finished {
+            // errors can't be reported from drop
+            let _ = self.cancel_inner();
+        }
+    }
+}
+
+/// Document id within a database, never 0
+///
+/// Ids of documents in a database come from Xapian, others are made with `DocId::new`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DocId(u32);
+
+impl DocId {
+    /// Id `did`, `None` for 0 which Xapian uses for "no document"
+    pub fn new(did: u32) -> Option<DocId> {
+        if did == 0 {
+            None
+        } else {
+            Some(DocId(did))
+        }
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl From<NonZeroU32> for DocId {
+    fn from(did: NonZeroU32) -> Self {
+        DocId(did.get())
+    }
+}
+
+impl From<DocId> for NonZeroU32 {
+    fn from(did: DocId) -> Self {
+        NonZeroU32::new(did.0).expect("DocId is never 0")
+    }
+}
+
+impl From<DocId> for u32 {
+    fn from(did: DocId) -> Self {
+        did.0
+    }
+}
+
+impl Display for DocId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+pub struct WritableDatabase {
+    cxxp: UniquePtr<ffi::WritableDatabase>,
+    backend: Option<Backend>,
+}
+
+#[allow(unused_unsafe)]
+impl WritableDatabase {
+    pub fn new(path: &str, action: DbAction, backend: Backend) -> Result<Self, XError> {
+        OpenOptions::new().action(action).backend(backend).open(path)
+    }
+
+    /// Open with the action, durability flags and backend given by `options`
+    pub fn open(path: &str, options: &OpenOptions) -> Result<Self, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::new_writable_database_with_flags(path, options.bits(), &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(Self {
+                    cxxp: obj,
+                    backend: Backend::in_use(path, options.flags),
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Backend of the opened database, which for an existing database may
+    /// differ from the one requested
+    pub fn get_backend(&self) -> Option<Backend> {
+        self.backend
+    }
+
+    /// `Database` handle reading through this writer, it sees changes not yet committed.
+    /// The handle shares the writer's internals, which stay open and locked while it is alive.
+    pub fn as_database(&self) -> Result<Database, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::writable_database_as_database(&self.cxxp, &mut err);
+
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(Database {
+                    cxxp: obj,
+                    backend: self.backend,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn delete_document(&mut self, unique_term: impl AsRef<[u8]>) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::delete_document(self.cxxp.pin_mut(), unique_term.as_ref(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace every document indexed by `unique_term` with `doc`, or add it if there are none.
+    /// Returns the docid `doc` is stored under.
+    pub fn replace_document(&mut self, unique_term: impl AsRef<[u8]>, doc: &mut Document) -> Result<DocId, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let did = ffi::replace_document(self.cxxp.pin_mut(), unique_term.as_ref(), doc.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            Ok(DocId(did))
+        }
+    }
+
+    /// Add `doc` under the next unused docid
+    pub fn add_document(&mut self, doc: &mut Document) -> Result<DocId, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let did = ffi::add_document(self.cxxp.pin_mut(), doc.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            Ok(DocId(did))
+        }
+    }
+
+    /// Store `doc` under `did`, replacing the document there if any
+    pub fn replace_document_by_id(&mut self, did: DocId, doc: &mut Document) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::replace_document_by_id(self.cxxp.pin_mut(), did.0, doc.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails with `XapianErrorKind::DocNotFoundError` if there is no document `did`
+    pub fn delete_document_by_id(&mut self, did: DocId) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::delete_document_by_id(self.cxxp.pin_mut(), did.0, &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Start a transaction, its changes are applied atomically by `Transaction::commit`
+    /// and discarded if the guard is dropped without committing.
+    /// A `flushed` transaction also commits pending changes before and after it.
+    pub fn transaction(&mut self, flushed: bool) -> Result<Transaction<'_>, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::begin_transaction(self.cxxp.pin_mut(), flushed, &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(Transaction {
+            db: self,
+            finished: false,
+        })
+    }
+
+    pub fn commit(&mut self) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::commit(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_doccount(&mut self) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::get_doccount(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                Err(err.into())
+            } else {
+                Ok(res)
+            }
+        }
+    }
+
+    /// Document with id `did`, fails with `XapianErrorKind::DocNotFoundError` if there is none
+    pub fn get_document(&self, did: DocId) -> Result<Document, XError> {
+        self.get_document_with_flags(did, false)
+    }
+
+    /// Document with id `did` opened with `DOC_ASSUME_VALID`, see `Database::get_document_lazy`
+    pub fn get_document_lazy(&self, did: DocId) -> Result<Document, XError> {
+        self.get_document_with_flags(did, true)
+    }
+
+    fn get_document_with_flags(&self, did: DocId, assume_valid: bool) -> Result<Document, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let doc = ffi::writable_database_get_document(&self.cxxp, did.0, assume_valid, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(Document {
+                    cxxp: doc,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+}
+
+pub struct Document {
+    cxxp: UniquePtr<ffi::Document>,
+}
+
+#[allow(unused_unsafe)]
+impl Document {
+    pub fn new() -> Result<Self, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::new_document(&mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(Self {
+                    cxxp: obj,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn add_string(&mut self, slot: u32, data: impl AsRef<[u8]>) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::add_string(self.cxxp.pin_mut(), slot, data.as_ref(), &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_int(&mut self, slot: u32, data: i32) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::add_int(self.cxxp.pin_mut(), slot, data, &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_long(&mut self, slot: u32, data: i64) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::add_long(self.cxxp.pin_mut(), slot, data, &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_float(&mut self, slot: u32, data: f32) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::add_float(self.cxxp.pin_mut(), slot, data, &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_double(&mut self, slot: u32, data: f64) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::add_double(self.cxxp.pin_mut(), slot, data, &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_data(&mut self, data: impl AsRef<[u8]>) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::set_data(self.cxxp.pin_mut(), data.as_ref(), &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_boolean_term(&mut self, data: impl AsRef<[u8]>) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::add_boolean_term(self.cxxp.pin_mut(), data.as_ref(), &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a term without positional information, increasing its wdf by `wdf_inc`
+    pub fn add_term(&mut self, term: impl AsRef<[u8]>, wdf_inc: u32) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::add_term(self.cxxp.pin_mut(), term.as_ref(), wdf_inc, &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Add an occurrence of a term at position `pos`, increasing its wdf by `wdf_inc`
+    pub fn add_posting(&mut self, term: impl AsRef<[u8]>, pos: u32, wdf_inc: u32) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::add_posting(self.cxxp.pin_mut(), term.as_ref(), pos, wdf_inc, &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a term and all its postings
+    pub fn remove_term(&mut self, term: impl AsRef<[u8]>) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::remove_term(self.cxxp.pin_mut(), term.as_ref(), &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the occurrence of a term at position `pos`, decreasing its wdf by `wdf_dec`
+    pub fn remove_posting(&mut self, term: impl AsRef<[u8]>, pos: u32, wdf_dec: u32) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::remove_posting(self.cxxp.pin_mut(), term.as_ref(), pos, wdf_dec, &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the occurrences of a term in a range of positions, decreasing its wdf
+    /// by `wdf_dec` for each removed posting. Returns the number of postings removed.
+    pub fn remove_postings(&mut self, term: impl AsRef<[u8]>, range: RangeInclusive<u32>, wdf_dec: u32) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::remove_postings(self.cxxp.pin_mut(), term.as_ref(), *range.start(), *range.end(), wdf_dec, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Remove all terms and postings
+    pub fn clear_terms(&mut self) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::clear_terms(self.cxxp.pin_mut(), &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove_value(&mut self, slot: u32) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::remove_value(self.cxxp.pin_mut(), slot, &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn clear_values(&mut self) -> Result<(), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+
+            ffi::clear_values(self.cxxp.pin_mut(), &mut err);
+
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_data(&self) -> Result<Vec<u8>, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let data = ffi::get_doc_data(&self.cxxp, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(data.as_bytes().to_vec())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Value stored in a slot, empty if the slot is unset
+    pub fn get_value(&self, slot: u32) -> Result<Vec<u8>, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let value = ffi::get_value(&self.cxxp, slot, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(value.as_bytes().to_vec())
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Decode a slot written by `add_double`, `None` if the slot is unset
+    pub fn get_value_f64(&self, slot: u32) -> Result<Option<f64>, XError> {
+        let value = self.get_value(slot)?;
+        if value.is_empty() {
+            return Ok(None);
+        }
+        sortable_unserialise(&value).map(Some)
+    }
+
+    /// Decode a slot written by `add_float`, `None` if the slot is unset
+    pub fn get_value_f32(&self, slot: u32) -> Result<Option<f32>, XError> {
+        Ok(self.get_value_f64(slot)?.map(|v| v as f32))
+    }
+
+    /// Decode a slot written by `add_long`, `None` if the slot is unset.
+    /// Values are stored as doubles, so integers beyond 2^53 lose precision.
+    pub fn get_value_i64(&self, slot: u32) -> Result<Option<i64>, XError> {
+        Ok(self.get_value_f64(slot)?.map(|v| v as i64))
+    }
+
+    /// Decode a slot written by `add_int`, `None` if the slot is unset
+    pub fn get_value_i32(&self, slot: u32) -> Result<Option<i32>, XError> {
+        Ok(self.get_value_f64(slot)?.map(|v| v as i32))
+    }
+
+    /// Document id in the database this document came from, `None` for a new document
+    pub fn get_docid(&self) -> Result<Option<DocId>, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::get_docid(&self.cxxp, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(DocId::new(res))
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn termlist_count(&self) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::termlist_count(&self.cxxp, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn values_count(&self) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let res = ffi::values_count(&self.cxxp, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Terms of the document in sorted order, with their wdf and positions
+    pub fn termlist(&self) -> Result<TermListIterator<'_>, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::document_termlist(&self.cxxp, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(TermListIterator {
+                    cxxp: obj,
+                    doc: PhantomData,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Set value slots of the document in ascending slot order
+    pub fn values(&self) -> Result<ValueIterator<'_>, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let obj = ffi::document_values(&self.cxxp, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(ValueIterator {
+                    cxxp: obj,
+                    doc: PhantomData,
+                })
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+}
+
+/// Term of a document's termlist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentTerm {
+    pub term: Vec<u8>,
+    /// Within-document frequency
+    pub wdf: u32,
+    pub positions: Vec<u32>,
+}
+
+/// Iterator over the terms of a document, which it borrows: for a new or modified
+/// document Xapian iterates its in-memory term map, which must not change or go away
+pub struct TermListIterator<'a> {
+    cxxp: UniquePtr<ffi::TermIter>,
+    doc: PhantomData<&'a Document>,
+}
+
+#[allow(unused_unsafe)]
+impl<'a> TermListIterator<'a> {
+    fn read(&mut self) -> Result<DocumentTerm, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let term = ffi::term_iter_term(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            let wdf = ffi::term_iter_wdf(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            let positions = ffi::term_iter_positions(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            ffi::term_iter_next(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+
+            Ok(DocumentTerm {
+                term: term.as_bytes().to_vec(),
+                wdf,
+                positions,
+            })
+        }
+    }
+}
+
+impl<'a> Iterator for TermListIterator<'a> {
+    type Item = Result<DocumentTerm, XError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cxxp.is_null() || ffi::term_iter_at_end(self.cxxp.pin_mut()) {
+            return None;
+        }
+
+        let res = self.read();
+        if res.is_err() {
+            // the C++ iterator is in an unknown state, stop here
+            self.cxxp = UniquePtr::null();
+        }
+        Some(res)
+    }
+}
+
+/// Iterator over the terms of a database, yields `(term, termfreq)`
+pub struct AllTermsIterator<'a> {
+    db: &'a Database,
+    cxxp: UniquePtr<ffi::TermIter>,
+}
+
+#[allow(unused_unsafe)]
+impl<'a> AllTermsIterator<'a> {
+    /// Also look up the collection frequency of each term, yields `(term, termfreq, collection_freq)`
+    pub fn with_collection_freq(self) -> impl Iterator<Item = Result<(Vec<u8>, u32, u32), XError>> + 'a {
+        let db = self.db;
+        self.map(move |res| {
+            let (term, termfreq) = res?;
+            let collection_freq = db.get_collection_freq(&term)?;
+            Ok((term, termfreq, collection_freq))
+        })
+    }
+
+    fn read(&mut self) -> Result<(Vec<u8>, u32), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let term = ffi::term_iter_term(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            let termfreq = ffi::term_iter_termfreq(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            ffi::term_iter_next(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+
+            Ok((term.as_bytes().to_vec(), termfreq))
+        }
+    }
+}
+
+impl<'a> Iterator for AllTermsIterator<'a> {
+    type Item = Result<(Vec<u8>, u32), XError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cxxp.is_null() || ffi::term_iter_at_end(self.cxxp.pin_mut()) {
+            return None;
+        }
+
+        let res = self.read();
+        if res.is_err() {
+            self.cxxp = UniquePtr::null();
+        }
+        Some(res)
+    }
+}
+
+/// Entry of a term's posting list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Posting {
+    pub docid: DocId,
+    /// Within-document frequency
+    pub wdf: u32,
+    pub doclength: u32,
+}
+
+pub struct PostingIterator {
+    cxxp: UniquePtr<ffi::PostingIter>,
+}
+
+#[allow(unused_unsafe)]
+impl PostingIterator {
+    /// Advance to the first posting with a docid of at least `did`, which the next call to `next()` returns.
+    /// Never moves backwards.
+    pub fn skip_to(&mut self, did: DocId) -> Result<(), XError> {
+        if self.cxxp.is_null() {
+            return Ok(());
+        }
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::posting_iter_skip_to(self.cxxp.pin_mut(), did.0, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                self.cxxp = UniquePtr::null();
+                Err(err.into())
+            }
+        }
+    }
+
+    fn read(&mut self) -> Result<Posting, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let docid = ffi::posting_iter_docid(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            let wdf = ffi::posting_iter_wdf(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            let doclength = ffi::posting_iter_doclength(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            ffi::posting_iter_next(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+
+            Ok(Posting {
+                docid: DocId(docid),
+                wdf,
+                doclength,
+            })
+        }
+    }
+}
+
+impl Iterator for PostingIterator {
+    type Item = Result<Posting, XError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cxxp.is_null() || ffi::posting_iter_at_end(self.cxxp.pin_mut()) {
+            return None;
+        }
+
+        let res = self.read();
+        if res.is_err() {
+            self.cxxp = UniquePtr::null();
+        }
+        Some(res)
+    }
+}
+
+pub struct PositionIterator {
+    cxxp: UniquePtr<ffi::PositionIter>,
+}
+
+#[allow(unused_unsafe)]
+impl PositionIterator {
+    /// Advance to the first position of at least `pos`, which the next call to `next()` returns.
+    /// Never moves backwards.
+    pub fn skip_to(&mut self, pos: u32) -> Result<(), XError> {
+        if self.cxxp.is_null() {
+            return Ok(());
+        }
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::position_iter_skip_to(self.cxxp.pin_mut(), pos, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                self.cxxp = UniquePtr::null();
+                Err(err.into())
+            }
+        }
+    }
+
+    fn read(&mut self) -> Result<u32, XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let pos = ffi::position_iter_pos(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            ffi::position_iter_next(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+
+            Ok(pos)
+        }
+    }
+}
+
+impl Iterator for PositionIterator {
+    type Item = Result<u32, XError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cxxp.is_null() || ffi::position_iter_at_end(self.cxxp.pin_mut()) {
+            return None;
+        }
 
-    pub fn add_string(&mut self, slot: u32, data: &str) -> Result<(), XError> {
-        unsafe {
-            let mut err = 0;
+        let res = self.read();
+        if res.is_err() {
+            self.cxxp = UniquePtr::null();
+        }
+        Some(res)
+    }
+}
 
-            ffi::add_string(self.cxxp.pin_mut(), slot, data, &mut err);
+/// Iterator over the value slots of a document, yields `(slot, value)`.
+/// Borrows the document for the same reason as `TermListIterator`.
+pub struct ValueIterator<'a> {
+    cxxp: UniquePtr<ffi::ValueIter>,
+    doc: PhantomData<&'a Document>,
+}
 
-            if err < 0 {
-                return Err(XError::Xapian(err));
+#[allow(unused_unsafe)]
+impl<'a> ValueIterator<'a> {
+    fn read(&mut self) -> Result<(u32, Vec<u8>), XError> {
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            let slot = ffi::value_iter_slot(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
             }
+            let value = ffi::value_iter_value(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            ffi::value_iter_next(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+
+            Ok((slot, value.as_bytes().to_vec()))
         }
-        Ok(())
     }
+}
 
-    pub fn add_int(&mut self, slot: u32, data: i32) -> Result<(), XError> {
-        unsafe {
-            let mut err = 0;
+impl<'a> Iterator for ValueIterator<'a> {
+    type Item = Result<(u32, Vec<u8>), XError>;
 
-            ffi::add_int(self.cxxp.pin_mut(), slot, data, &mut err);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cxxp.is_null() || ffi::value_iter_at_end(self.cxxp.pin_mut()) {
+            return None;
+        }
 
-            if err < 0 {
-                return Err(XError::Xapian(err));
-            }
+        let res = self.read();
+        if res.is_err() {
+            self.cxxp = UniquePtr::null();
         }
-        Ok(())
+        Some(res)
     }
+}
 
-    pub fn add_long(&mut self, slot: u32, data: i64) -> Result<(), XError> {
-        unsafe {
-            let mut err = 0;
-
-            ffi::add_long(self.cxxp.pin_mut(), slot, data, &mut err);
+/// Iterator over the values of one slot across the database
+pub struct ValueStreamIterator {
+    cxxp: UniquePtr<ffi::ValueIter>,
+}
 
-            if err < 0 {
-                return Err(XError::Xapian(err));
+#[allow(unused_unsafe)]
+impl ValueStreamIterator {
+    /// Advance to the first document of at least `did` with a value, which the next call to `next()` returns.
+    /// Never moves backwards.
+    pub fn skip_to(&mut self, did: DocId) -> Result<(), XError> {
+        if self.cxxp.is_null() {
+            return Ok(());
+        }
+        unsafe {
+            let mut err = ffi::FfiError::default();
+            ffi::value_iter_skip_to(self.cxxp.pin_mut(), did.0, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(())
+            } else {
+                self.cxxp = UniquePtr::null();
+                Err(err.into())
             }
         }
-        Ok(())
     }
 
-    pub fn add_double(&mut self, slot: u32, data: f64) -> Result<(), XError> {
+    /// Whether document `did` has a value in this slot, `did` must exist in the database.
+    /// Moves forward like `skip_to`, when `false` is returned the next call to `next()`
+    /// yields the first document after `did`.
+    pub fn check(&mut self, did: DocId) -> Result<bool, XError> {
+        if self.cxxp.is_null() {
+            return Ok(false);
+        }
         unsafe {
-            let mut err = 0;
-
-            ffi::add_double(self.cxxp.pin_mut(), slot, data, &mut err);
-
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            let mut err = ffi::FfiError::default();
+            let res = ffi::value_iter_check(self.cxxp.pin_mut(), did.0, &mut err);
+            if err.code == ffi::ErrorCode::Ok {
+                Ok(res)
+            } else {
+                self.cxxp = UniquePtr::null();
+                Err(err.into())
             }
         }
-        Ok(())
     }
 
-    pub fn set_data(&mut self, data: &str) -> Result<(), XError> {
+    fn read(&mut self) -> Result<(DocId, Vec<u8>), XError> {
         unsafe {
-            let mut err = 0;
-
-            ffi::set_data(self.cxxp.pin_mut(), data, &mut err);
-
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            let mut err = ffi::FfiError::default();
+            let docid = ffi::value_iter_docid(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+            let value = ffi::value_iter_value(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
             }
+            ffi::value_iter_next(self.cxxp.pin_mut(), &mut err);
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
+            }
+
+            Ok((DocId(docid), value.as_bytes().to_vec()))
         }
-        Ok(())
     }
+}
 
-    pub fn add_boolean_term(&mut self, data: &str) -> Result<(), XError> {
-        unsafe {
-            let mut err = 0;
+impl Iterator for ValueStreamIterator {
+    type Item = Result<(DocId, Vec<u8>), XError>;
 
-            ffi::add_boolean_term(self.cxxp.pin_mut(), data, &mut err);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cxxp.is_null() || ffi::value_iter_at_end(self.cxxp.pin_mut()) {
+            return None;
+        }
 
-            if err < 0 {
-                return Err(XError::Xapian(err));
-            }
+        let res = self.read();
+        if res.is_err() {
+            self.cxxp = UniquePtr::null();
         }
-        Ok(())
+        Some(res)
     }
 }
 
@@ -944,14 +3511,14 @@ pub struct Stem {
 impl Stem {
     pub fn new(lang: &str) -> Result<Self, XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             let obj = ffi::new_stem(lang, &mut err);
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(Self {
                     cxxp: obj,
                 })
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
@@ -965,14 +3532,14 @@ pub struct TermGenerator {
 impl TermGenerator {
     pub fn new() -> Result<Self> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             let obj = ffi::new_termgenerator(&mut err);
-            if err == 0 {
+            if err.code == ffi::ErrorCode::Ok {
                 Ok(Self {
                     cxxp: obj,
                 })
             } else {
-                Err(XError::Xapian(err))
+                Err(err.into())
             }
         }
     }
@@ -982,10 +3549,10 @@ impl TermGenerator {
 impl TermGenerator {
     pub fn set_stemmer(&mut self, stem: &mut Stem) -> Result<(), XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
             ffi::set_stemmer(self.cxxp.pin_mut(), stem.cxxp.pin_mut(), &mut err);
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
             }
         }
         Ok(())
@@ -993,12 +3560,12 @@ impl TermGenerator {
 
     pub fn set_document(&mut self, doc: &mut Document) -> Result<(), XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
 
             ffi::set_document(self.cxxp.pin_mut(), doc.cxxp.pin_mut(), &mut err);
 
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
             }
         }
         Ok(())
@@ -1006,12 +3573,12 @@ impl TermGenerator {
 
     pub fn index_text_with_prefix(&mut self, data: &str, prefix: &str) -> Result<(), XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
 
             ffi::index_text_with_prefix(self.cxxp.pin_mut(), data, prefix, &mut err);
 
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
             }
         }
         Ok(())
@@ -1019,12 +3586,12 @@ impl TermGenerator {
 
     pub fn index_text(&mut self, data: &str) -> Result<(), XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
 
             ffi::index_text(self.cxxp.pin_mut(), data, &mut err);
 
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
             }
         }
         Ok(())
@@ -1032,12 +3599,12 @@ impl TermGenerator {
 
     pub fn index_int(&mut self, data: i32, prefix: &str) -> Result<(), XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
 
             ffi::index_int(self.cxxp.pin_mut(), data, prefix, &mut err);
 
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
             }
         }
         Ok(())
@@ -1045,12 +3612,12 @@ impl TermGenerator {
 
     pub fn index_long(&mut self, data: i64, prefix: &str) -> Result<(), XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
 
             ffi::index_long(self.cxxp.pin_mut(), data, prefix, &mut err);
 
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
             }
         }
         Ok(())
@@ -1058,12 +3625,12 @@ impl TermGenerator {
 
     pub fn index_float(&mut self, data: f32, prefix: &str) -> Result<(), XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
 
             ffi::index_float(self.cxxp.pin_mut(), data, prefix, &mut err);
 
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
             }
         }
         Ok(())
@@ -1071,58 +3638,253 @@ impl TermGenerator {
 
     pub fn index_double(&mut self, data: f64, prefix: &str) -> Result<(), XError> {
         unsafe {
-            let mut err = 0;
+            let mut err = ffi::FfiError::default();
 
             ffi::index_double(self.cxxp.pin_mut(), data, prefix, &mut err);
 
-            if err < 0 {
-                return Err(XError::Xapian(err));
+            if err.code != ffi::ErrorCode::Ok {
+                return Err(err.into());
             }
         }
         Ok(())
     }
 }
 
-pub fn get_xapian_err_type(errcode: i8) -> &'static str {
-    match errcode {
-        0 => "AssertionError",
-        -1 => "InvalidArgumentError",
-        -2 => "InvalidOperationError",
-        -3 => "UnimplementedError",
-        -4 => "DatabaseError",
-        -5 => "DatabaseCorruptError",
-        -6 => "DatabaseCreateError",
-        -7 => "DatabaseLockError",
-        -10 => "DatabaseModifiedError",
-        -11 => "DatabaseOpeningError",
-        -12 => "DatabaseVersionError",
-        -13 => "DocNotFoundError",
-        -14 => "FeatureUnavailableError",
-        -15 => "InternalError",
-        -16 => "NetworkError",
-        -17 => "NetworkTimeoutError",
-        -20 => "QueryParserError",
-        -21 => "SerialisationError",
-        -22 => "RangeError",
-        -23 => "WildcardError",
-        -24 => "DatabaseNotFoundError",
-        -25 => "DatabaseClosedError",
-        _ => "Unknown",
+/// Convert a number to a string which sorts in the same order, as used by
+/// `Document::add_double` and friends for value slots
+pub fn sortable_serialise(value: f64) -> Result<Vec<u8>, XError> {
+    #[allow(unused_unsafe)]
+    unsafe {
+        let mut err = ffi::FfiError::default();
+        let res = ffi::sortable_serialise_double(value, &mut err);
+        if err.code == ffi::ErrorCode::Ok {
+            Ok(res.as_bytes().to_vec())
+        } else {
+            Err(err.into())
+        }
+    }
+}
+
+/// Convert a string produced by `sortable_serialise` back to a number
+pub fn sortable_unserialise(serialised: &[u8]) -> Result<f64, XError> {
+    #[allow(unused_unsafe)]
+    unsafe {
+        let mut err = ffi::FfiError::default();
+        let res = ffi::sortable_unserialise_bytes(serialised, &mut err);
+        if err.code == ffi::ErrorCode::Ok {
+            Ok(res)
+        } else {
+            Err(err.into())
+        }
     }
 }
 
+pub fn get_xapian_err_type(errcode: i8) -> &'static str {
+    XapianErrorKind::from_code(errcode).name()
+}
+
 pub type Result<T, E = XError> = std::result::Result<T, E>;
 
+impl Default for ffi::ErrorCode {
+    fn default() -> Self {
+        ffi::ErrorCode::Ok
+    }
+}
+
+/// Xapian exception class, numbered by the `ErrorCode` table shared with xapian-bind.cc
+#[repr(i8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum XapianErrorKind {
+    DatabaseModifiedError = ffi::ErrorCode::DatabaseModifiedError.repr,
+    DatabaseLockError = ffi::ErrorCode::DatabaseLockError.repr,
+    LogicError = ffi::ErrorCode::LogicError.repr,
+    AssertionError = ffi::ErrorCode::AssertionError.repr,
+    InvalidArgumentError = ffi::ErrorCode::InvalidArgumentError.repr,
+    InvalidOperationError = ffi::ErrorCode::InvalidOperationError.repr,
+    UnimplementedError = ffi::ErrorCode::UnimplementedError.repr,
+    RuntimeError = ffi::ErrorCode::RuntimeError.repr,
+    DatabaseError = ffi::ErrorCode::DatabaseError.repr,
+    DatabaseCorruptError = ffi::ErrorCode::DatabaseCorruptError.repr,
+    DatabaseCreateError = ffi::ErrorCode::DatabaseCreateError.repr,
+    DatabaseOpeningError = ffi::ErrorCode::DatabaseOpeningError.repr,
+    DatabaseVersionError = ffi::ErrorCode::DatabaseVersionError.repr,
+    DocNotFoundError = ffi::ErrorCode::DocNotFoundError.repr,
+    FeatureUnavailableError = ffi::ErrorCode::FeatureUnavailableError.repr,
+    InternalError = ffi::ErrorCode::InternalError.repr,
+    NetworkError = ffi::ErrorCode::NetworkError.repr,
+    NetworkTimeoutError = ffi::ErrorCode::NetworkTimeoutError.repr,
+    QueryParserError = ffi::ErrorCode::QueryParserError.repr,
+    RangeError = ffi::ErrorCode::RangeError.repr,
+    SerialisationError = ffi::ErrorCode::SerialisationError.repr,
+    WildcardError = ffi::ErrorCode::WildcardError.repr,
+    DatabaseNotFoundError = ffi::ErrorCode::DatabaseNotFoundError.repr,
+    DatabaseClosedError = ffi::ErrorCode::DatabaseClosedError.repr,
+    /// An exception class this crate doesn't know about, or a thrown value which isn't a std::exception
+    Unknown = ffi::ErrorCode::Unknown.repr,
+}
+
+impl XapianErrorKind {
+    pub const ALL: [XapianErrorKind; 25] = [
+        XapianErrorKind::DatabaseModifiedError,
+        XapianErrorKind::DatabaseLockError,
+        XapianErrorKind::LogicError,
+        XapianErrorKind::AssertionError,
+        XapianErrorKind::InvalidArgumentError,
+        XapianErrorKind::InvalidOperationError,
+        XapianErrorKind::UnimplementedError,
+        XapianErrorKind::RuntimeError,
+        XapianErrorKind::DatabaseError,
+        XapianErrorKind::DatabaseCorruptError,
+        XapianErrorKind::DatabaseCreateError,
+        XapianErrorKind::DatabaseOpeningError,
+        XapianErrorKind::DatabaseVersionError,
+        XapianErrorKind::DocNotFoundError,
+        XapianErrorKind::FeatureUnavailableError,
+        XapianErrorKind::InternalError,
+        XapianErrorKind::NetworkError,
+        XapianErrorKind::NetworkTimeoutError,
+        XapianErrorKind::QueryParserError,
+        XapianErrorKind::RangeError,
+        XapianErrorKind::SerialisationError,
+        XapianErrorKind::WildcardError,
+        XapianErrorKind::DatabaseNotFoundError,
+        XapianErrorKind::DatabaseClosedError,
+        XapianErrorKind::Unknown,
+    ];
+
+    /// Map a shared error code to its kind, codes not in the table map to `Unknown`
+    pub fn from_code(code: i8) -> Self {
+        XapianErrorKind::ALL.iter().copied().find(|kind| kind.code() == code).unwrap_or(XapianErrorKind::Unknown)
+    }
+
+    pub fn code(self) -> i8 {
+        self as i8
+    }
+
+    /// Name of the Xapian exception class, as returned by `Xapian::Error::get_type()`
+    pub fn name(self) -> &'static str {
+        use XapianErrorKind::*;
+        match self {
+            DatabaseModifiedError => "DatabaseModifiedError",
+            DatabaseLockError => "DatabaseLockError",
+            LogicError => "LogicError",
+            AssertionError => "AssertionError",
+            InvalidArgumentError => "InvalidArgumentError",
+            InvalidOperationError => "InvalidOperationError",
+            UnimplementedError => "UnimplementedError",
+            RuntimeError => "RuntimeError",
+            DatabaseError => "DatabaseError",
+            DatabaseCorruptError => "DatabaseCorruptError",
+            DatabaseCreateError => "DatabaseCreateError",
+            DatabaseOpeningError => "DatabaseOpeningError",
+            DatabaseVersionError => "DatabaseVersionError",
+            DocNotFoundError => "DocNotFoundError",
+            FeatureUnavailableError => "FeatureUnavailableError",
+            InternalError => "InternalError",
+            NetworkError => "NetworkError",
+            NetworkTimeoutError => "NetworkTimeoutError",
+            QueryParserError => "QueryParserError",
+            RangeError => "RangeError",
+            SerialisationError => "SerialisationError",
+            WildcardError => "WildcardError",
+            DatabaseNotFoundError => "DatabaseNotFoundError",
+            DatabaseClosedError => "DatabaseClosedError",
+            Unknown => "Unknown",
+        }
+    }
+}
+
+impl From<ffi::ErrorCode> for XapianErrorKind {
+    fn from(code: ffi::ErrorCode) -> Self {
+        XapianErrorKind::from_code(code.repr)
+    }
+}
+
+impl Display for XapianErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Xapian exception reported across the FFI boundary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XapianError {
+    pub kind: XapianErrorKind,
+    /// Message explaining the error, `Xapian::Error::get_msg()`
+    pub msg: String,
+    /// Context of the error, e.g. the database path, `Xapian::Error::get_context()`
+    pub context: String,
+    /// Description of the errno which caused the error, if any
+    pub error_string: Option<String>,
+}
+
+impl XapianError {
+    pub fn new(kind: XapianErrorKind) -> Self {
+        XapianError {
+            kind,
+            msg: String::new(),
+            context: String::new(),
+            error_string: None,
+        }
+    }
+}
+
+impl Display for XapianError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if !self.msg.is_empty() {
+            write!(f, ": {}", self.msg)?;
+        }
+        if !self.context.is_empty() {
+            write!(f, " (context: {})", self.context)?;
+        }
+        if let Some(error_string) = &self.error_string {
+            write!(f, " ({})", error_string)?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for XapianError {}
+
+impl From<ffi::FfiError> for XapianError {
+    fn from(err: ffi::FfiError) -> Self {
+        XapianError {
+            kind: err.code.into(),
+            msg: err.msg,
+            context: err.context,
+            error_string: if err.error_string.is_empty() {
+                None
+            } else {
+                Some(err.error_string)
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum XError {
-    Xapian(i8),
+    Xapian(XapianError),
+    /// A std::exception other than Xapian::Error, with its `what()` message
+    Cxx(String),
     Io(io::Error),
 }
 
+impl XError {
+    /// Kind of the underlying Xapian exception, if this is a Xapian error
+    pub fn xapian_kind(&self) -> Option<XapianErrorKind> {
+        match self {
+            XError::Xapian(err) => Some(err.kind),
+            _ => None,
+        }
+    }
+}
+
 impl Display for XError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            XError::Xapian(err) => write!(f, "xapian err={}", err),
+            XError::Xapian(err) => err.fmt(f),
+            XError::Cxx(msg) => write!(f, "C++ exception: {}", msg),
             XError::Io(err) => err.fmt(f),
         }
     }
@@ -1131,8 +3893,9 @@ impl Display for XError {
 impl StdError for XError {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match self {
+            XError::Xapian(err) => Some(err),
+            XError::Cxx(_) => None,
             XError::Io(err) => Some(err),
-            _ => None,
         }
     }
 }
@@ -1143,14 +3906,1049 @@ impl From<io::Error> for XError {
     }
 }
 
+impl From<ffi::FfiError> for XError {
+    fn from(err: ffi::FfiError) -> Self {
+        if err.code == ffi::ErrorCode::CxxException {
+            XError::Cxx(err.msg)
+        } else {
+            XError::Xapian(err.into())
+        }
+    }
+}
+
 impl From<i8> for XError {
     fn from(err: i8) -> Self {
-        XError::Xapian(err)
+        XError::Xapian(XapianError::new(XapianErrorKind::from_code(err)))
     }
 }
 
 impl From<XError> for i8 {
     fn from(err: XError) -> i8 {
+        match err {
+            XError::Xapian(err) => err.kind.code(),
+            XError::Cxx(_) => ffi::ErrorCode::CxxException.repr,
+            XError::Io(_) => XapianErrorKind::Unknown.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Error reported by set_error for the exception `test_throw` throws for `kind`
+    #[cfg(feature = "test-throw")]
+    fn throw(kind: &str, msg: &str) -> XError {
+        let mut err = ffi::FfiError::default();
+        ffi::test_throw(kind, msg, &mut err);
+        assert!(err.code != ffi::ErrorCode::Ok, "{} wasn't reported", kind);
         err.into()
     }
+
+    fn xapian_error(err: XError) -> XapianError {
+        match err {
+            XError::Xapian(err) => err,
+            err => panic!("expected a Xapian error, got {:?}", err),
+        }
+    }
+
+    fn doc_id(did: u32) -> DocId {
+        DocId::new(did).unwrap()
+    }
+
+    fn inmemory() -> WritableDatabase {
+        WritableDatabase::new("", DbAction::CreateOrOpen, Backend::InMemory).unwrap()
+    }
+
+    // Enquire over `db` for the query string `query`
+    fn enquire(db: &mut Database, query: &str) -> Enquire {
+        let mut query = QueryParser::new().unwrap().parse_query(query, 0).unwrap();
+        let mut enquire = db.new_enquire().unwrap();
+        enquire.set_query(&mut query).unwrap();
+        enquire
+    }
+
+    // Path in the temp dir for a test's on-disk database, unique to the test and process
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("xapian-rusty-{}-{}", name, std::process::id()))
+    }
+
+    #[cfg(feature = "test-throw")]
+    #[test]
+    fn every_error_class_maps_to_its_kind() {
+        for &kind in XapianErrorKind::ALL.iter().filter(|&&kind| kind != XapianErrorKind::Unknown) {
+            // test_throw throws the class named `kind`, whose get_type() is that name
+            let err = xapian_error(throw(kind.name(), "thrown"));
+            assert_eq!(err.kind, kind);
+            assert_eq!(err.kind.name(), kind.name());
+            assert_eq!(err.msg, "thrown");
+        }
+    }
+
+    #[cfg(feature = "test-throw")]
+    #[test]
+    fn unknown_error_class_maps_to_unknown() {
+        let err = xapian_error(throw("FutureError", "thrown"));
+        assert_eq!(err.kind, XapianErrorKind::Unknown);
+        assert_eq!(err.kind.name(), "Unknown");
+        assert_eq!(err.msg, "thrown");
+    }
+
+    #[test]
+    fn error_codes_round_trip() {
+        let mut codes: Vec<i8> = XapianErrorKind::ALL.iter().map(|kind| kind.code()).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), XapianErrorKind::ALL.len());
+
+        for &kind in XapianErrorKind::ALL.iter() {
+            assert_eq!(XapianErrorKind::from_code(kind.code()), kind);
+            assert_eq!(XapianErrorKind::from(ffi::ErrorCode { repr: kind.code() }), kind);
+
+            let code = i8::from(XError::Xapian(XapianError::new(kind)));
+            assert_eq!(code, kind.code());
+            assert_eq!(XError::from(code).xapian_kind(), Some(kind));
+        }
+
+        assert_eq!(XapianErrorKind::Unknown.code(), ffi::ErrorCode::Unknown.repr);
+        assert_eq!(XapianErrorKind::from_code(ffi::ErrorCode::Ok.repr), XapianErrorKind::Unknown);
+        assert_eq!(XapianErrorKind::from_code(ffi::ErrorCode::CxxException.repr), XapianErrorKind::Unknown);
+        assert_eq!(XapianErrorKind::from_code(100), XapianErrorKind::Unknown);
+    }
+
+    #[test]
+    fn cxx_and_io_errors_to_code() {
+        assert_eq!(i8::from(XError::Cxx("out of cheese".to_string())), ffi::ErrorCode::CxxException.repr);
+        assert_eq!(i8::from(XError::Io(io::Error::from(io::ErrorKind::NotFound))), XapianErrorKind::Unknown.code());
+    }
+
+    #[cfg(feature = "test-throw")]
+    #[test]
+    fn xapian_error_keeps_message() {
+        let err = xapian_error(throw("DocNotFoundError", "no such document"));
+        assert_eq!(
+            err,
+            XapianError {
+                kind: XapianErrorKind::DocNotFoundError,
+                msg: "no such document".to_string(),
+                context: String::new(),
+                error_string: None,
+            }
+        );
+    }
+
+    #[cfg(feature = "test-throw")]
+    #[test]
+    fn std_exception_becomes_cxx() {
+        match throw("std::exception", "out of cheese") {
+            XError::Cxx(msg) => assert_eq!(msg, "out of cheese"),
+            err => panic!("expected XError::Cxx, got {:?}", err),
+        }
+    }
+
+    #[cfg(feature = "test-throw")]
+    #[test]
+    fn non_exception_becomes_unknown() {
+        let err = xapian_error(throw("int", ""));
+        assert_eq!(err.kind, XapianErrorKind::Unknown);
+        assert_eq!(err.msg, "unknown C++ exception");
+    }
+
+    #[test]
+    fn missing_database() {
+        let path = std::env::temp_dir().join(format!("xapian-rusty-missing-{}", std::process::id()));
+        let err = Database::new_with_path(path.to_str().unwrap(), Backend::Auto).err().expect("opened a missing database");
+        let err = xapian_error(err);
+        assert_eq!(err.kind, XapianErrorKind::DatabaseNotFoundError);
+        assert!(!err.msg.is_empty());
+    }
+
+    #[test]
+    fn bad_query_string() {
+        let mut qp = QueryParser::new().unwrap();
+        let err = xapian_error(qp.parse_query("apple AND", FeatureFlag::FlagBoolean as i16).err().expect("parsed a bad query"));
+        assert_eq!(err.kind, XapianErrorKind::QueryParserError);
+        assert_eq!(err.msg, "Syntax: <expression> AND <expression>");
+    }
+
+    #[test]
+    fn document_read_api() {
+        let mut doc = Document::new().unwrap();
+        doc.set_data("some data").unwrap();
+        doc.add_posting("banana", 2, 1).unwrap();
+        doc.add_posting("apple", 1, 1).unwrap();
+        doc.add_term("apple", 2).unwrap();
+        doc.add_string(3, "three").unwrap();
+        doc.add_string(1, "one").unwrap();
+
+        assert_eq!(doc.get_docid().unwrap(), None);
+        assert_eq!(doc.get_data().unwrap(), b"some data");
+        assert_eq!(doc.termlist_count().unwrap(), 2);
+        assert_eq!(doc.values_count().unwrap(), 2);
+        assert_eq!(doc.get_value(2).unwrap(), b"");
+
+        // both iterators borrow the document, which stays readable meanwhile
+        let doc = &doc;
+        let mut terms = doc.termlist().unwrap();
+        let mut values = doc.values().unwrap();
+        assert_eq!(
+            terms.next().unwrap().unwrap(),
+            DocumentTerm {
+                term: b"apple".to_vec(),
+                wdf: 3,
+                positions: vec![1],
+            }
+        );
+        assert_eq!(values.next().unwrap().unwrap(), (1, b"one".to_vec()));
+        assert_eq!(doc.get_value(3).unwrap(), b"three");
+        assert_eq!(
+            terms.next().unwrap().unwrap(),
+            DocumentTerm {
+                term: b"banana".to_vec(),
+                wdf: 1,
+                positions: vec![2],
+            }
+        );
+        assert!(terms.next().is_none());
+        assert_eq!(values.next().unwrap().unwrap(), (3, b"three".to_vec()));
+        assert!(values.next().is_none());
+    }
+
+    #[test]
+    fn open_options_bits() {
+        assert_eq!(OpenOptions::new().bits(), 0);
+        assert_eq!(OpenOptions::new().action(DbAction::Open).no_sync(true).retry_lock(true).bits(), 0x03 | 0x04 | 0x40);
+        assert_eq!(OpenOptions::new().dangerous(true).dangerous(false).full_sync(true).bits(), 0x08);
+        assert_eq!(OpenOptions::new().action(DbAction::CreateOrOverwrite).backend(Backend::Auto).bits(), 0x01);
+        assert_eq!(OpenOptions::new().backend(Backend::Glass).backend(Backend::InMemory).bits(), 0x400);
+        assert_eq!(OpenOptions::new().backend(Backend::Stub).flags(DbFlags::NO_TERMLIST).bits(), 0x300 | 0x20);
+        // a backend passed as raw flags replaces the backend instead of being ORed into it
+        assert_eq!(OpenOptions::new().backend(Backend::Glass).flags(Backend::Chert.flags()).bits(), 0x200);
+        assert_eq!(OpenOptions::new().flags(Backend::Chert.flags() | DbFlags::NO_SYNC).backend(Backend::Glass).bits(), 0x100 | 0x04);
+    }
+
+    #[test]
+    fn backend_from_flags() {
+        for &backend in &[Backend::Auto, Backend::Glass, Backend::Chert, Backend::InMemory, Backend::Stub] {
+            assert_eq!(Backend::from_flags(backend.flags()), backend);
+            assert_eq!(Backend::from_flags(backend.flags() | DbFlags::NO_SYNC | DbFlags::DANGEROUS), backend);
+        }
+        assert_eq!(Backend::from_flags(DbFlags::empty()), Backend::Auto);
+        // not a backend Xapian 1.4 knows
+        assert_eq!(Backend::from_flags(DbFlags(0x500)), Backend::Auto);
+    }
+
+    fn parse_scheme(s: &str) -> Result<WeightingScheme, ParseWeightingSchemeError> {
+        s.parse()
+    }
+
+    fn parse_error(msg: &str) -> Result<WeightingScheme, ParseWeightingSchemeError> {
+        Err(ParseWeightingSchemeError(msg.to_string()))
+    }
+
+    #[test]
+    fn weighting_scheme_defaults() {
+        use WeightingScheme::*;
+        assert_eq!(parse_scheme("bool"), Ok(Bool));
+        assert_eq!(parse_scheme("coord"), Ok(Coord));
+        assert_eq!(
+            parse_scheme("tfidf"),
+            Ok(TfIdf {
+                normalizations: "ntn".to_string(),
+            })
+        );
+        assert_eq!(parse_scheme("bm25"), Ok(WeightingScheme::default()));
+        assert_eq!(
+            parse_scheme("bm25+"),
+            Ok(BM25Plus {
+                k1: 1.0,
+                k2: 0.0,
+                k3: 1.0,
+                b: 0.5,
+                min_normlen: 0.5,
+                delta: 1.0,
+            })
+        );
+        assert_eq!(
+            parse_scheme("trad"),
+            Ok(Trad {
+                k: 1.0,
+            })
+        );
+        assert_eq!(
+            parse_scheme("inl2"),
+            Ok(InL2 {
+                c: 1.0,
+            })
+        );
+        assert_eq!(
+            parse_scheme("ifb2"),
+            Ok(IfB2 {
+                c: 1.0,
+            })
+        );
+        assert_eq!(
+            parse_scheme("ineb2"),
+            Ok(IneB2 {
+                c: 1.0,
+            })
+        );
+        assert_eq!(
+            parse_scheme("bb2"),
+            Ok(BB2 {
+                c: 1.0,
+            })
+        );
+        assert_eq!(parse_scheme("dlh"), Ok(DLH));
+        assert_eq!(
+            parse_scheme("pl2"),
+            Ok(PL2 {
+                c: 1.0,
+            })
+        );
+        assert_eq!(
+            parse_scheme("pl2+"),
+            Ok(PL2Plus {
+                c: 1.0,
+                delta: 0.8,
+            })
+        );
+        assert_eq!(parse_scheme("dph"), Ok(DPH));
+        assert_eq!(
+            parse_scheme("lm"),
+            Ok(LM {
+                param_log: 0.0,
+                smoothing: LMSmoothing::TwoStage,
+                param_smoothing1: 0.7,
+                param_smoothing2: 2000.0,
+            })
+        );
+    }
+
+    #[test]
+    fn weighting_scheme_parameters() {
+        use WeightingScheme::*;
+        assert_eq!(
+            parse_scheme("tfidf bnn"),
+            Ok(TfIdf {
+                normalizations: "bnn".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_scheme("  bm25 1.2 0 1 0.75 0.25 "),
+            Ok(BM25 {
+                k1: 1.2,
+                k2: 0.0,
+                k3: 1.0,
+                b: 0.75,
+                min_normlen: 0.25,
+            })
+        );
+        // trailing parameters keep their defaults
+        assert_eq!(
+            parse_scheme("bm25+ 2 0.5"),
+            Ok(BM25Plus {
+                k1: 2.0,
+                k2: 0.5,
+                k3: 1.0,
+                b: 0.5,
+                min_normlen: 0.5,
+                delta: 1.0,
+            })
+        );
+        assert_eq!(
+            parse_scheme("pl2+ 2"),
+            Ok(PL2Plus {
+                c: 2.0,
+                delta: 0.8,
+            })
+        );
+        assert_eq!(
+            parse_scheme("trad 0.5"),
+            Ok(Trad {
+                k: 0.5,
+            })
+        );
+        // smoothings by number or name, the second parameter defaults by smoothing
+        assert_eq!(
+            parse_scheme("lm 100 2"),
+            Ok(LM {
+                param_log: 100.0,
+                smoothing: LMSmoothing::Dirichlet,
+                param_smoothing1: 0.7,
+                param_smoothing2: 0.05,
+            })
+        );
+        assert_eq!(
+            parse_scheme("lm 0 jelinek_mercer 0.5 0.2"),
+            Ok(LM {
+                param_log: 0.0,
+                smoothing: LMSmoothing::JelinekMercer,
+                param_smoothing1: 0.5,
+                param_smoothing2: 0.2,
+            })
+        );
+        for (name, smoothing) in &[
+            ("two_stage", LMSmoothing::TwoStage),
+            ("dirichlet", LMSmoothing::Dirichlet),
+            ("absolute_discount", LMSmoothing::AbsoluteDiscount),
+            ("jelinek_mercer", LMSmoothing::JelinekMercer),
+            ("dirichlet_plus", LMSmoothing::DirichletPlus),
+        ] {
+            for param in &[name.to_string(), (*smoothing as i32).to_string()] {
+                match parse_scheme(&format!("lm 0 {}", param)) {
+                    Ok(LM {
+                        smoothing: parsed,
+                        ..
+                    }) => assert_eq!(parsed, *smoothing),
+                    res => panic!("lm 0 {} parsed as {:?}", param, res),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weighting_scheme_errors() {
+        assert_eq!(parse_scheme(""), parse_error("empty weighting scheme"));
+        assert_eq!(parse_scheme("  "), parse_error("empty weighting scheme"));
+        assert_eq!(parse_scheme("BM25"), parse_error("unknown weighting scheme \"BM25\""));
+        assert_eq!(parse_scheme("custom"), parse_error("unknown weighting scheme \"custom\""));
+        assert_eq!(parse_scheme("bm25 1 x"), parse_error("invalid parameter \"x\" for bm25"));
+        assert_eq!(parse_scheme("lm 0 6"), parse_error("invalid smoothing \"6\" for lm"));
+        assert_eq!(parse_scheme("lm 0 dirichlet x"), parse_error("invalid parameter \"x\" for lm"));
+        assert_eq!(parse_scheme("bool 1"), parse_error("too many parameters for bool, starting at \"1\""));
+        assert_eq!(parse_scheme("tfidf ntn bnn"), parse_error("too many parameters for tfidf, starting at \"bnn\""));
+        assert_eq!(parse_scheme("trad 1 2"), parse_error("too many parameters for trad, starting at \"2\""));
+    }
+
+    #[test]
+    fn weighting_scheme_display_round_trip() {
+        assert_eq!(WeightingScheme::default().to_string(), "bm25 1 0 1 0.5 0.5");
+        assert_eq!(parse_scheme("lm").unwrap().to_string(), "lm 0 1 0.7 2000");
+
+        let strings = [
+            "bool",
+            "coord",
+            "tfidf ntn",
+            "tfidf bnn",
+            "bm25 1.2 0 1 0.75 0.5",
+            "bm25+ 1 0 1 0.5 0.5 1.5",
+            "trad 1",
+            "inl2 2",
+            "ifb2 0.5",
+            "ineb2 1",
+            "bb2 3",
+            "dlh",
+            "pl2 1",
+            "pl2+ 1 0.8",
+            "dph",
+            "lm 0 1 0.7 2000",
+            "lm 10 3 0.1 0.05",
+            "lm 0 5 0.7 0.05",
+        ];
+        for &s in strings.iter() {
+            let scheme = parse_scheme(s).unwrap();
+            assert_eq!(scheme.to_string(), s);
+            assert_eq!(parse_scheme(&scheme.to_string()), Ok(scheme));
+        }
+    }
+
+    struct ConstWeight;
+
+    impl Weight for ConstWeight {
+        fn clone_box(&self) -> Box<dyn Weight> {
+            Box::new(ConstWeight)
+        }
+
+        fn stats_needed(&self) -> StatFlags {
+            StatFlags::empty()
+        }
+
+        fn init(&mut self, _factor: f64, _stats: &WeightStats) {}
+
+        fn get_sumpart(&self, _wdf: u32, _doclen: u32, _uniqterms: u32) -> f64 {
+            1.0
+        }
+
+        fn get_maxpart(&self) -> f64 {
+            1.0
+        }
+
+        fn name(&self) -> String {
+            "const".to_string()
+        }
+    }
+
+    #[test]
+    fn custom_weighting_scheme_has_no_string_form() {
+        let scheme = WeightingScheme::custom(ConstWeight);
+        assert_eq!(scheme.to_string(), "const");
+        assert!(parse_scheme(&scheme.to_string()).is_err());
+    }
+
+    fn doc_with_term(term: &str) -> Document {
+        let mut doc = Document::new().unwrap();
+        doc.add_term(term, 1).unwrap();
+        doc
+    }
+
+    // the inmemory backend applies changes as they are made and can't cancel them,
+    // so transactions are tested on glass
+    #[test]
+    fn transaction_cancel_and_commit() {
+        let path = temp_path("transaction");
+        let mut db = WritableDatabase::new(path.to_str().unwrap(), DbAction::CreateOrOverwrite, Backend::Glass).unwrap();
+        db.add_document(&mut doc_with_term("apple")).unwrap();
+        db.commit().unwrap();
+
+        {
+            let mut txn = db.transaction(false).unwrap();
+            txn.add_document(&mut doc_with_term("banana")).unwrap();
+            assert_eq!(txn.get_doccount().unwrap(), 2);
+        }
+        assert_eq!(db.get_doccount().unwrap(), 1);
+
+        let mut txn = db.transaction(true).unwrap();
+        txn.add_document(&mut doc_with_term("cherry")).unwrap();
+        txn.cancel().unwrap();
+        assert_eq!(db.get_doccount().unwrap(), 1);
+
+        let mut txn = db.transaction(false).unwrap();
+        txn.add_document(&mut doc_with_term("banana")).unwrap();
+        txn.commit().unwrap();
+        assert_eq!(db.get_doccount().unwrap(), 2);
+        db.commit().unwrap();
+        drop(db);
+
+        let db = Database::new_with_path(path.to_str().unwrap(), Backend::Auto).unwrap();
+        assert_eq!(db.get_doccount().unwrap(), 2);
+        assert_eq!(db.get_termfreq("apple").unwrap(), 1);
+        assert_eq!(db.get_termfreq("banana").unwrap(), 1);
+        assert_eq!(db.get_termfreq("cherry").unwrap(), 0);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    fn next_value(values: &mut ValueStreamIterator) -> Option<(u32, String)> {
+        values.next().map(|res| {
+            let (did, value) = res.unwrap();
+            (did.into(), String::from_utf8(value).unwrap())
+        })
+    }
+
+    #[test]
+    fn valuestream_over_sparse_slot() {
+        let mut db = inmemory();
+        for did in 1..=5 {
+            let mut doc = Document::new().unwrap();
+            // slot 1 is set on the odd documents
+            if did % 2 == 1 {
+                doc.add_string(1, format!("value {}", did)).unwrap();
+            }
+            db.add_document(&mut doc).unwrap();
+        }
+        let db = db.as_database().unwrap();
+
+        let all = db.valuestream(1).unwrap().map(|res| res.unwrap()).collect::<Vec<_>>();
+        assert_eq!(
+            all,
+            vec![(doc_id(1), b"value 1".to_vec()), (doc_id(3), b"value 3".to_vec()), (doc_id(5), b"value 5".to_vec())]
+        );
+        assert!(db.valuestream(2).unwrap().next().is_none());
+
+        let mut values = db.valuestream(1).unwrap();
+        values.skip_to(doc_id(2)).unwrap();
+        assert_eq!(next_value(&mut values), Some((3, "value 3".to_string())));
+        // already past 4
+        values.skip_to(doc_id(4)).unwrap();
+        assert_eq!(next_value(&mut values), Some((5, "value 5".to_string())));
+        assert_eq!(next_value(&mut values), None);
+        values.skip_to(doc_id(1)).unwrap();
+        assert_eq!(next_value(&mut values), None);
+
+        let mut values = db.valuestream(1).unwrap();
+        assert!(!values.check(doc_id(2)).unwrap());
+        assert_eq!(next_value(&mut values), Some((3, "value 3".to_string())));
+        assert!(values.check(doc_id(5)).unwrap());
+        assert_eq!(next_value(&mut values), Some((5, "value 5".to_string())));
+        assert_eq!(next_value(&mut values), None);
+        assert!(!values.check(doc_id(5)).unwrap());
+
+        let mut values = db.valuestream(1).unwrap();
+        assert!(!values.check(doc_id(4)).unwrap());
+        assert_eq!(next_value(&mut values), Some((5, "value 5".to_string())));
+
+        let mut values = db.valuestream(2).unwrap();
+        assert!(!values.check(doc_id(1)).unwrap());
+        assert_eq!(next_value(&mut values), None);
+    }
+
+    #[test]
+    fn detect_backend() {
+        let dir = temp_path("detect");
+        let dir_with = |name: &str, files: &[&str]| {
+            let path = dir.join(name);
+            std::fs::create_dir_all(&path).unwrap();
+            for file in files {
+                std::fs::write(path.join(file), b"").unwrap();
+            }
+            path
+        };
+
+        assert_eq!(Backend::detect(dir.join("missing")), None);
+        assert_eq!(Backend::detect(dir_with("empty", &[])), None);
+        assert_eq!(Backend::detect(dir_with("glass", &["iamglass"])), Some(Backend::Glass));
+        assert_eq!(Backend::detect(dir_with("chert", &["iamchert"])), Some(Backend::Chert));
+        assert_eq!(Backend::detect(dir_with("stub", &["XAPIANDB"])), Some(Backend::Stub));
+        // Xapian opens the database in a directory before looking for a stub file
+        assert_eq!(Backend::detect(dir_with("glass-and-stub", &["XAPIANDB", "iamglass"])), Some(Backend::Glass));
+        assert_eq!(Backend::detect(dir_with("chert-and-stub", &["XAPIANDB", "iamchert"])), Some(Backend::Chert));
+        assert_eq!(Backend::detect(dir_with("glass-and-chert", &["iamchert", "iamglass"])), Some(Backend::Glass));
+
+        let single = dir.join("single.glass");
+        let mut contents = GLASS_SINGLE_FILE_MAGIC.to_vec();
+        contents.extend_from_slice(&[0; 64]);
+        std::fs::write(&single, contents).unwrap();
+        assert_eq!(Backend::detect(&single), Some(Backend::Glass));
+
+        let stub = dir.join("stub.file");
+        std::fs::write(&stub, "auto /srv/db\n").unwrap();
+        assert_eq!(Backend::detect(&stub), Some(Backend::Stub));
+        // shorter than the magic
+        std::fs::write(&stub, "auto x").unwrap();
+        assert_eq!(Backend::detect(&stub), Some(Backend::Stub));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn docid(hit: Option<Result<Hit, XError>>) -> Option<u32> {
+        hit.map(|hit| hit.unwrap().docid.into())
+    }
+
+    #[test]
+    fn mset_iterates_from_both_ends() {
+        let mut db = inmemory();
+        for did in 1..=5 {
+            let mut doc = doc_with_term("apple");
+            doc.set_data(format!("doc {}", did)).unwrap();
+            db.add_document(&mut doc).unwrap();
+        }
+        let mut db = db.as_database().unwrap();
+        let mut enquire = enquire(&mut db, "apple");
+        // every hit weighs the same, so they rank in docid order
+        enquire.set_weighting_scheme(&WeightingScheme::Bool).unwrap();
+        let mset = enquire.get_mset(0, 10).unwrap();
+
+        assert_eq!(mset.iter().len(), 5);
+        let forward = mset
+            .iter()
+            .map(|hit| {
+                let hit = hit.unwrap();
+                (hit.rank, u32::from(hit.docid), hit.get_document_data().unwrap())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(forward, (1..=5).map(|did| (did - 1, did, format!("doc {}", did))).collect::<Vec<_>>());
+        let backward = mset.iter().rev().map(|hit| hit.unwrap().docid.into()).collect::<Vec<u32>>();
+        assert_eq!(backward, vec![5, 4, 3, 2, 1]);
+
+        let mut hits = mset.iter();
+        assert_eq!(docid(hits.next()), Some(1));
+        assert_eq!(docid(hits.next_back()), Some(5));
+        assert_eq!(hits.len(), 3);
+        assert_eq!(docid(hits.next_back()), Some(4));
+        assert_eq!(docid(hits.next()), Some(2));
+        assert_eq!(docid(hits.next()), Some(3));
+        assert_eq!(hits.len(), 0);
+        assert_eq!(docid(hits.next()), None);
+        assert_eq!(docid(hits.next_back()), None);
+
+        // a page of the results ranks from its first hit
+        let page = enquire.get_mset(1, 3).unwrap();
+        let hits = page.iter().map(|hit| hit.unwrap()).map(|hit| (hit.rank, u32::from(hit.docid))).collect::<Vec<_>>();
+        assert_eq!(hits, vec![(1, 2), (2, 3), (3, 4)]);
+        assert_eq!(docid(page.iter().next_back()), Some(4));
+        assert_eq!(page.get(2).unwrap().map(|hit| hit.rank), Some(3));
+        assert!(page.get(3).unwrap().is_none());
+    }
+
+    #[test]
+    fn binary_terms_data_and_values() {
+        // not valid UTF-8, with NULs and a newline
+        let data = b"\x00\xff\xfe\nbin\x00";
+        let term = b"Q\x00\xff\x80id";
+
+        let mut db = inmemory();
+        let mut doc = doc_with_term("apple");
+        doc.set_data(&data[..]).unwrap();
+        doc.add_string(0, &data[..]).unwrap();
+        doc.add_boolean_term(&term[..]).unwrap();
+        let did = db.add_document(&mut doc).unwrap();
+
+        let doc = db.get_document(did).unwrap();
+        assert_eq!(doc.get_data().unwrap(), data);
+        assert_eq!(doc.get_value(0).unwrap(), data);
+        let terms = doc.termlist().unwrap().map(|term| term.unwrap().term).collect::<Vec<_>>();
+        assert_eq!(terms, vec![term.to_vec(), b"apple".to_vec()]);
+
+        let mut read = db.as_database().unwrap();
+        assert_eq!(read.get_termfreq(&term[..]).unwrap(), 1);
+        let mset = enquire(&mut read, "apple").get_mset(0, 10).unwrap();
+        let hit = mset.iter().next().unwrap().unwrap();
+        assert_eq!(hit.get_document_data_bytes().unwrap(), data);
+        assert_eq!(hit.get_value(0).unwrap(), data);
+        assert_eq!(hit.get_document_data().unwrap(), String::from_utf8_lossy(data));
+
+        let mut doc = Document::new().unwrap();
+        doc.set_data(&b"\xc0\xc1"[..]).unwrap();
+        doc.add_boolean_term(&term[..]).unwrap();
+        assert_eq!(db.replace_document(&term[..], &mut doc).unwrap(), did);
+        assert_eq!(db.get_document(did).unwrap().get_data().unwrap(), b"\xc0\xc1");
+        assert_eq!(db.get_doccount().unwrap(), 1);
+
+        db.delete_document(&term[..]).unwrap();
+        assert_eq!(db.get_doccount().unwrap(), 0);
+    }
+
+    fn document_terms(doc: &Document) -> Vec<(String, u32, Vec<u32>)> {
+        doc.termlist()
+            .unwrap()
+            .map(|term| {
+                let term = term.unwrap();
+                (String::from_utf8(term.term).unwrap(), term.wdf, term.positions)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn document_mutation() {
+        let mut doc = Document::new().unwrap();
+        for pos in 1..=5 {
+            doc.add_posting("code", pos, 1).unwrap();
+        }
+        doc.add_posting("sku", 6, 1).unwrap();
+        doc.add_term("tag", 1).unwrap();
+        doc.add_string(0, "zero").unwrap();
+        doc.add_string(1, "one").unwrap();
+
+        doc.remove_posting("code", 1, 1).unwrap();
+        assert_eq!(doc.remove_postings("code", 3..=4, 1).unwrap(), 2);
+        assert_eq!(doc.remove_postings("code", 10..=20, 1).unwrap(), 0);
+        doc.remove_term("tag").unwrap();
+        doc.remove_value(0).unwrap();
+        assert_eq!(
+            document_terms(&doc),
+            vec![("code".to_string(), 2, vec![2, 5]), ("sku".to_string(), 1, vec![6])]
+        );
+        assert_eq!(doc.get_value(0).unwrap(), b"");
+        assert_eq!(doc.values_count().unwrap(), 1);
+
+        let err = xapian_error(doc.remove_term("missing").expect_err("removed a missing term"));
+        assert_eq!(err.kind, XapianErrorKind::InvalidArgumentError);
+        let err = xapian_error(doc.remove_posting("code", 3, 1).expect_err("removed a missing posting"));
+        assert_eq!(err.kind, XapianErrorKind::InvalidArgumentError);
+
+        let mut db = inmemory();
+        let did = db.add_document(&mut doc).unwrap();
+        let read = db.as_database().unwrap();
+        assert_eq!(read.get_termfreq("tag").unwrap(), 0);
+        assert_eq!(read.get_collection_freq("code").unwrap(), 2);
+        assert_eq!(read.positionlist(did, "code").unwrap().map(|pos| pos.unwrap()).collect::<Vec<_>>(), vec![2, 5]);
+        assert_eq!(read.get_value_freq(0).unwrap(), 0);
+        assert_eq!(read.get_value_freq(1).unwrap(), 1);
+
+        let mut doc = db.get_document(did).unwrap();
+        doc.clear_terms().unwrap();
+        doc.clear_values().unwrap();
+        assert_eq!(doc.termlist_count().unwrap(), 0);
+        assert_eq!(doc.values_count().unwrap(), 0);
+        db.replace_document_by_id(did, &mut doc).unwrap();
+        assert!(!read.term_exists("code").unwrap());
+        assert_eq!(read.get_value_freq(1).unwrap(), 0);
+        assert_eq!(read.get_doccount().unwrap(), 1);
+    }
+
+    #[test]
+    fn typed_values() {
+        let mut db = inmemory();
+        for &(int, price) in &[(-7, 19.99), (3, 5.0), (0, -0.25)] {
+            let mut doc = doc_with_term("apple");
+            doc.add_int(0, int).unwrap();
+            doc.add_long(1, i64::from(int) << 40).unwrap();
+            doc.add_float(2, int as f32 / 2.0).unwrap();
+            doc.add_double(3, price).unwrap();
+            db.add_document(&mut doc).unwrap();
+        }
+
+        let doc = db.get_document(doc_id(1)).unwrap();
+        assert_eq!(doc.get_value_i32(0).unwrap(), Some(-7));
+        assert_eq!(doc.get_value_i64(1).unwrap(), Some(-7 << 40));
+        assert_eq!(doc.get_value_f32(2).unwrap(), Some(-3.5));
+        assert_eq!(doc.get_value_f64(3).unwrap(), Some(19.99));
+        assert_eq!(doc.get_value(3).unwrap(), sortable_serialise(19.99).unwrap());
+        assert_eq!(doc.get_value_f64(4).unwrap(), None);
+        assert_eq!(doc.get_value_i64(4).unwrap(), None);
+
+        // the encoding sorts bytewise in numeric order
+        let encoded = [-1e10, -1.0, -0.25, 0.0, 0.5, 3.0, 1e10].iter().map(|&v| sortable_serialise(v).unwrap()).collect::<Vec<_>>();
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(sorted, encoded);
+        assert_eq!(sortable_unserialise(&encoded[2]).unwrap(), -0.25);
+
+        let mut read = db.as_database().unwrap();
+        assert_eq!(sortable_unserialise(&read.get_value_lower_bound(3).unwrap()).unwrap(), -0.25);
+        assert_eq!(sortable_unserialise(&read.get_value_upper_bound(3).unwrap()).unwrap(), 19.99);
+        let mut enquire = enquire(&mut read, "apple");
+        enquire.set_sort_by_value(3, false).unwrap();
+        let mset = enquire.get_mset(0, 10).unwrap();
+        let hits = mset
+            .iter()
+            .map(|hit| {
+                let hit = hit.unwrap();
+                (hit.get_value_f64(3).unwrap(), hit.get_value_i64(0).unwrap())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(hits, vec![(Some(-0.25), Some(0)), (Some(5.0), Some(3)), (Some(19.99), Some(-7))]);
+    }
+
+    #[test]
+    fn mset_and_hit_statistics() {
+        let mut db = inmemory();
+        for wdf in 1..=3 {
+            let mut doc = Document::new().unwrap();
+            doc.add_term("apple", wdf).unwrap();
+            doc.add_term("filler", 2).unwrap();
+            doc.set_data(format!("apple {}", wdf)).unwrap();
+            db.add_document(&mut doc).unwrap();
+        }
+        db.add_document(&mut doc_with_term("banana")).unwrap();
+        let mut read = db.as_database().unwrap();
+        let mset = enquire(&mut read, "apple").get_mset(0, 10).unwrap();
+
+        assert_eq!(mset.size().unwrap(), 3);
+        assert_eq!(mset.get_matches_lower_bound().unwrap(), 3);
+        assert_eq!(mset.get_matches_estimated().unwrap(), 3);
+        assert_eq!(mset.get_matches_upper_bound().unwrap(), 3);
+        assert_eq!(mset.get_termfreq("apple").unwrap(), 3);
+        assert!(mset.get_termweight("apple").unwrap() > 0.0);
+
+        let hits = mset.iter().map(|hit| hit.unwrap()).collect::<Vec<_>>();
+        assert_eq!(hits.iter().map(|hit| hit.rank).collect::<Vec<_>>(), vec![0, 1, 2]);
+        let mut docids = hits.iter().map(|hit| hit.docid.get()).collect::<Vec<_>>();
+        docids.sort();
+        assert_eq!(docids, vec![1, 2, 3]);
+        assert!(hits.windows(2).all(|pair| pair[0].weight >= pair[1].weight));
+        assert!(hits[2].weight > 0.0);
+        assert_eq!(mset.get_max_attained().unwrap(), hits[0].weight);
+        assert!(mset.get_max_possible().unwrap() >= hits[0].weight);
+        assert_eq!(hits[0].percent, 100);
+        assert!(hits.iter().all(|hit| hit.percent > 0 && hit.percent <= 100));
+
+        for hit in &hits {
+            let doc = hit.get_document().unwrap();
+            assert_eq!(doc.get_docid().unwrap(), Some(hit.docid));
+            assert_eq!(hit.get_document_data().unwrap(), format!("apple {}", hit.docid.get()));
+            // not collapsed or sorted by key
+            assert_eq!(hit.get_collapse_count().unwrap(), 0);
+            assert_eq!(hit.get_collapse_key().unwrap(), b"");
+            assert_eq!(hit.get_sort_key().unwrap(), b"");
+        }
+    }
+
+    #[test]
+    fn database_statistics() {
+        let mut db = inmemory();
+        let read = db.as_database().unwrap();
+        assert_eq!(read.get_doccount().unwrap(), 0);
+        assert_eq!(read.get_lastdocid().unwrap(), None);
+        assert_eq!(read.get_total_length().unwrap(), 0);
+
+        for terms in &[&[("apple", 2), ("banana", 1)][..], &[("apple", 1)], &[("cherry", 4)]] {
+            let mut doc = Document::new().unwrap();
+            for &(term, wdf) in terms.iter() {
+                doc.add_term(term, wdf).unwrap();
+            }
+            db.add_document(&mut doc).unwrap();
+        }
+        db.delete_document_by_id(doc_id(2)).unwrap();
+
+        assert_eq!(read.get_doccount().unwrap(), 2);
+        // ids of deleted documents stay used
+        assert_eq!(read.get_lastdocid().unwrap(), Some(doc_id(3)));
+        assert_eq!(read.get_total_length().unwrap(), 7);
+        assert_eq!(read.get_avlength().unwrap(), 3.5);
+        assert_eq!(read.get_doclength(doc_id(1)).unwrap(), 3);
+        assert_eq!(read.get_unique_terms(doc_id(1)).unwrap(), 2);
+        assert_eq!(read.get_doclength(doc_id(3)).unwrap(), 4);
+        let err = xapian_error(read.get_doclength(doc_id(2)).expect_err("length of a deleted document"));
+        assert_eq!(err.kind, XapianErrorKind::DocNotFoundError);
+
+        assert_eq!(read.get_termfreq("apple").unwrap(), 1);
+        assert_eq!(read.get_collection_freq("apple").unwrap(), 2);
+        assert_eq!(read.get_collection_freq("cherry").unwrap(), 4);
+        assert!(read.term_exists("banana").unwrap());
+        assert!(!read.term_exists("durian").unwrap());
+        assert_eq!(read.get_termfreq("durian").unwrap(), 0);
+
+        // bounds may be loose, but never exclude the actual lengths
+        assert!(read.get_doclength_lower_bound().unwrap() <= 3);
+        assert!(read.get_doclength_upper_bound().unwrap() >= 4);
+        assert!(read.get_wdf_upper_bound("cherry").unwrap() >= 4);
+    }
+
+    #[test]
+    fn allterms_with_prefix() {
+        let mut db = inmemory();
+        for &(colour, wdf) in &[("XPred", 2), ("XPblue", 1), ("XPred", 1)] {
+            let mut doc = Document::new().unwrap();
+            doc.add_boolean_term(colour).unwrap();
+            doc.add_term("apple", wdf).unwrap();
+            db.add_document(&mut doc).unwrap();
+        }
+        db.add_document(&mut doc_with_term("XQsize")).unwrap();
+        let read = db.as_database().unwrap();
+
+        let terms = |prefix: &str| {
+            read.allterms(prefix)
+                .unwrap()
+                .map(|res| {
+                    let (term, termfreq) = res.unwrap();
+                    (String::from_utf8(term).unwrap(), termfreq)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(terms("XP"), vec![("XPblue".to_string(), 1), ("XPred".to_string(), 2)]);
+        assert_eq!(terms("XPr"), vec![("XPred".to_string(), 2)]);
+        assert_eq!(terms("XPred"), vec![("XPred".to_string(), 2)]);
+        assert_eq!(terms("XR"), vec![]);
+        assert_eq!(
+            terms(""),
+            vec![
+                ("XPblue".to_string(), 1),
+                ("XPred".to_string(), 2),
+                ("XQsize".to_string(), 1),
+                ("apple".to_string(), 3),
+            ]
+        );
+
+        // boolean terms have no occurrences
+        let freqs = read.allterms("").unwrap().with_collection_freq().map(|res| res.unwrap()).collect::<Vec<_>>();
+        assert_eq!(
+            freqs,
+            vec![
+                (b"XPblue".to_vec(), 1, 0),
+                (b"XPred".to_vec(), 2, 0),
+                (b"XQsize".to_vec(), 1, 1),
+                (b"apple".to_vec(), 3, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn documents_by_docid() {
+        let mut db = inmemory();
+        for name in &["first", "second"] {
+            let mut doc = doc_with_term(name);
+            doc.set_data(name).unwrap();
+            db.add_document(&mut doc).unwrap();
+        }
+        db.delete_document_by_id(doc_id(1)).unwrap();
+        let read = db.as_database().unwrap();
+
+        let doc = db.get_document(doc_id(2)).unwrap();
+        assert_eq!(doc.get_docid().unwrap(), Some(doc_id(2)));
+        assert_eq!(doc.get_data().unwrap(), b"second");
+        assert_eq!(read.get_document(doc_id(2)).unwrap().get_data().unwrap(), b"second");
+
+        // contents of a lazy document are read on first use
+        for doc in &[db.get_document_lazy(doc_id(2)).unwrap(), read.get_document_lazy(doc_id(2)).unwrap()] {
+            assert_eq!(doc.get_docid().unwrap(), Some(doc_id(2)));
+            assert_eq!(doc.get_data().unwrap(), b"second");
+            assert_eq!(doc.termlist_count().unwrap(), 1);
+        }
+
+        for &did in &[1, 3] {
+            let err = db.get_document(doc_id(did)).err().expect("got a missing document");
+            assert_eq!(err.xapian_kind(), Some(XapianErrorKind::DocNotFoundError));
+            let err = read.get_document(doc_id(did)).err().expect("got a missing document");
+            assert_eq!(err.xapian_kind(), Some(XapianErrorKind::DocNotFoundError));
+        }
+        let err = db.delete_document_by_id(doc_id(1)).expect_err("deleted a missing document");
+        assert_eq!(err.xapian_kind(), Some(XapianErrorKind::DocNotFoundError));
+    }
+
+    fn search_docids(db: &mut Database, query: &str) -> Vec<u32> {
+        let mset = enquire(db, query).get_mset(0, 10).unwrap();
+        let mut docids = mset.iter().map(|hit| hit.unwrap().docid.get()).collect::<Vec<_>>();
+        docids.sort();
+        docids
+    }
+
+    #[test]
+    fn search_through_writer() {
+        let mut db = inmemory();
+        db.add_document(&mut doc_with_term("apple")).unwrap();
+        let mut read = db.as_database().unwrap();
+        assert_eq!(read.get_backend(), Some(Backend::InMemory));
+        assert_eq!(search_docids(&mut read, "apple"), vec![1]);
+
+        // the handle reads through the writer, so later changes show up without a reopen
+        db.add_document(&mut doc_with_term("apple")).unwrap();
+        db.add_document(&mut doc_with_term("banana")).unwrap();
+        assert_eq!(search_docids(&mut read, "apple"), vec![1, 2]);
+        db.delete_document_by_id(doc_id(1)).unwrap();
+        assert_eq!(search_docids(&mut read, "apple"), vec![2]);
+        assert_eq!(search_docids(&mut db.as_database().unwrap(), "banana"), vec![3]);
+    }
+
+    #[test]
+    fn collapse_on_value() {
+        let mut db = inmemory();
+        for feed in &["a", "a", "b", "a", ""] {
+            let mut doc = doc_with_term("apple");
+            if !feed.is_empty() {
+                doc.add_string(0, feed).unwrap();
+            }
+            db.add_document(&mut doc).unwrap();
+        }
+        let mut read = db.as_database().unwrap();
+        let mut enquire = enquire(&mut read, "apple");
+        // equal weights rank in docid order
+        enquire.set_weighting_scheme(&WeightingScheme::Bool).unwrap();
+
+        let hits = |enquire: &mut Enquire| {
+            let mset = enquire.get_mset(0, 10).unwrap();
+            mset.iter()
+                .map(|hit| {
+                    let hit = hit.unwrap();
+                    (hit.docid.get(), hit.get_collapse_key().unwrap(), hit.get_collapse_count().unwrap())
+                })
+                .collect::<Vec<_>>()
+        };
+
+        enquire.set_collapse_key(0, 1).unwrap();
+        assert_eq!(hits(&mut enquire), vec![(1, b"a".to_vec(), 2), (3, b"b".to_vec(), 0), (5, vec![], 0)]);
+
+        enquire.set_collapse_key(0, 2).unwrap();
+        let docids = hits(&mut enquire).into_iter().map(|(did, ..)| did).collect::<Vec<_>>();
+        assert_eq!(docids, vec![1, 2, 3, 5]);
+
+        enquire.clear_collapse_key().unwrap();
+        assert_eq!(hits(&mut enquire), (1..=5).map(|did| (did, vec![], 0)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn panicking_key_maker_becomes_cxx() {
+        let mut db = inmemory();
+        db.add_document(&mut doc_with_term("apple")).unwrap();
+        let mut read = db.as_database().unwrap();
+        let mut enquire = enquire(&mut read, "apple");
+        enquire.set_sort_by_key(KeyMaker::from_fn(|_| panic!("no key")).unwrap(), false).unwrap();
+
+        match enquire.get_mset(0, 10) {
+            Err(XError::Cxx(msg)) => assert_eq!(msg, "KeyMaker panicked: no key"),
+            Err(err) => panic!("expected XError::Cxx, got {:?}", err),
+            Ok(_) => panic!("the key maker wasn't called"),
+        }
+    }
 }
@@ -0,0 +1,122 @@
+// Several threads searching one on-disk database while another thread updates it.
+// Each thread opens its own handles, the results they return are owned and may be sent on.
+
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use xapian_rusty::*;
+
+const WRITES: u32 = 200;
+const READERS: usize = 4;
+const MIN_SEARCHES: usize = 20;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn results_are_send_and_sync() {
+    assert_send_sync::<String>();
+    assert_send_sync::<Vec<u8>>();
+    assert_send_sync::<DocId>();
+    assert_send_sync::<Posting>();
+    assert_send_sync::<DocumentTerm>();
+    assert_send_sync::<WeightingScheme>();
+    assert_send_sync::<XapianError>();
+    assert_send_sync::<XError>();
+}
+
+fn new_doc(did: u32) -> Result<Document, XError> {
+    let mut doc = Document::new()?;
+    doc.set_data(format!("doc {}", did))?;
+    doc.add_term("apple", 1)?;
+    Ok(doc)
+}
+
+// Number of hits, each checked against the data it was written with
+fn search(db: &mut Database, qp: &mut QueryParser) -> Result<usize, XError> {
+    let mut apple = qp.parse_query("apple", 0)?;
+    let mut banana = qp.parse_query("banana", 0)?;
+    // both descriptions are held at once, they used to share one buffer
+    let apple_desc = apple.get_description();
+    let banana_desc = banana.get_description();
+    assert!(apple_desc.contains("apple"), "{}", apple_desc);
+    assert!(banana_desc.contains("banana"), "{}", banana_desc);
+
+    let mut enquire = db.new_enquire()?;
+    enquire.set_query(&mut apple)?;
+    let mset = enquire.get_mset(0, 50)?;
+    let hits = mset
+        .iter()
+        .map(|hit| {
+            let hit = hit?;
+            Ok((hit.docid, hit.get_document_data()?))
+        })
+        .collect::<Result<Vec<_>, XError>>()?;
+    for (docid, data) in &hits {
+        assert_eq!(*data, format!("doc {}", docid));
+    }
+    Ok(hits.len())
+}
+
+#[test]
+fn search_while_updating() {
+    let dir = std::env::temp_dir().join(format!("xapian-rusty-threads-{}", std::process::id()));
+    let path = dir.to_str().unwrap().to_string();
+
+    let mut db = WritableDatabase::new(&path, DbAction::CreateOrOverwrite, Backend::Glass).unwrap();
+    db.add_document(&mut new_doc(1).unwrap()).unwrap();
+    db.commit().unwrap();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let start = Arc::new(Barrier::new(READERS + 1));
+
+    let readers: Vec<_> = (0..READERS)
+        .map(|_| {
+            let path = path.clone();
+            let done = done.clone();
+            let start = start.clone();
+            thread::spawn(move || {
+                let mut db = Database::new_with_path(&path, Backend::Auto).unwrap();
+                let mut qp = QueryParser::new().unwrap();
+                start.wait();
+
+                let mut searches = 0;
+                while !done.load(Ordering::Acquire) || searches < MIN_SEARCHES {
+                    match search(&mut db, &mut qp) {
+                        Ok(hits) => assert!(hits >= 1),
+                        // the writer committed over the revision being read
+                        Err(err) if err.xapian_kind() == Some(XapianErrorKind::DatabaseModifiedError) => db.reopen().unwrap(),
+                        Err(err) => panic!("search failed: {}", err),
+                    }
+                    searches += 1;
+                    if searches % 5 == 0 {
+                        db.reopen().unwrap();
+                    }
+                }
+                searches
+            })
+        })
+        .collect();
+
+    start.wait();
+    for did in 2..=WRITES + 1 {
+        assert_eq!(Some(db.add_document(&mut new_doc(did).unwrap()).unwrap()), DocId::new(did));
+        if did % 10 == 0 {
+            db.commit().unwrap();
+        }
+    }
+    db.commit().unwrap();
+    done.store(true, Ordering::Release);
+
+    for reader in readers {
+        assert!(reader.join().unwrap() >= MIN_SEARCHES);
+    }
+
+    let mut db = Database::new_with_path(&path, Backend::Auto).unwrap();
+    assert_eq!(db.get_doccount().unwrap(), WRITES + 1);
+    assert_eq!(search(&mut db, &mut QueryParser::new().unwrap()).unwrap(), 50);
+
+    drop(db);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -5,771 +5,1994 @@
 #include <xapian.h>
 #include <string>
 #include <string.h>
+#include <stdexcept>
 
 using namespace Xapian;
 
-const int BRASS     = 1;
-const int CHERT     = 2;
-const int IN_MEMORY = 3;
-
-char get_err_code(const char *type)
-{
-    signed char err = 0;
-
-    if (strcmp(type, (char *)"DatabaseModifiedError") == 0)
-        err = -1;
-    else if (strcmp(type, (char *)"DatabaseLockError") == 0)
-        err = -2;
-    else if (strcmp(type, (char *)"LogicError") == 0)
-        err = -3;
-    else if (strcmp(type, (char *)"AssertionError") == 0)
-        err = -4;
-    else if (strcmp(type, (char *)"InvalidArgumentError") == 0)
-        err = -5;
-    else if (strcmp(type, (char *)"InvalidOperationError") == 0)
-        err = -6;
-    else if (strcmp(type, (char *)"UnimplementedError") == 0)
-        err = -7;
-    else if (strcmp(type, (char *)"RuntimeError") == 0)
-        err = -8;
-    else if (strcmp(type, (char *)"DatabaseError") == 0)
-        err = -9;
-    else if (strcmp(type, (char *)"DatabaseCorruptError") == 0)
-        err = -10;
-    else if (strcmp(type, (char *)"DatabaseCreateError") == 0)
-        err = -11;
-    else if (strcmp(type, (char *)"DatabaseOpeningError") == 0)
-        err = -12;
-    else if (strcmp(type, (char *)"DatabaseVersionError") == 0)
-        err = -13;
-    else if (strcmp(type, (char *)"DocNotFoundError") == 0)
-        err = -14;
-    else if (strcmp(type, (char *)"FeatureUnavailableError") == 0)
-        err = -15;
-    else if (strcmp(type, (char *)"InternalError") == 0)
-        err = -16;
-    else if (strcmp(type, (char *)"NetworkError") == 0)
-        err = -17;
-    else if (strcmp(type, (char *)"NetworkTimeoutError") == 0)
-        err = -18;
-    else if (strcmp(type, (char *)"QueryParserError") == 0)
-        err = -19;
-    else if (strcmp(type, (char *)"RangeError") == 0)
-        err = -20;
-    else if (strcmp(type, (char *)"SerialisationError") == 0)
-        err = -21;
-
-    return err;
-}
-
-/** Open for read/write; create if no db exists. */
-const int DB_CREATE_OR_OPEN = 1;
-/** Create a new database; fail if db exists. */
-const int DB_CREATE = 2;
-/** Overwrite existing db; create if none exists. */
-const int DB_CREATE_OR_OVERWRITE = 3;
-
-std::unique_ptr<Database> new_database(int8_t &err)
-{
-    try
-    {
-        err = 0;
+// Codes come from the ErrorCode enum shared with src/lib.rs
+ErrorCode get_err_code(const char *type)
+{
+    if (strcmp(type, "DatabaseModifiedError") == 0)
+        return ErrorCode::DatabaseModifiedError;
+    else if (strcmp(type, "DatabaseLockError") == 0)
+        return ErrorCode::DatabaseLockError;
+    else if (strcmp(type, "LogicError") == 0)
+        return ErrorCode::LogicError;
+    else if (strcmp(type, "AssertionError") == 0)
+        return ErrorCode::AssertionError;
+    else if (strcmp(type, "InvalidArgumentError") == 0)
+        return ErrorCode::InvalidArgumentError;
+    else if (strcmp(type, "InvalidOperationError") == 0)
+        return ErrorCode::InvalidOperationError;
+    else if (strcmp(type, "UnimplementedError") == 0)
+        return ErrorCode::UnimplementedError;
+    else if (strcmp(type, "RuntimeError") == 0)
+        return ErrorCode::RuntimeError;
+    else if (strcmp(type, "DatabaseError") == 0)
+        return ErrorCode::DatabaseError;
+    else if (strcmp(type, "DatabaseCorruptError") == 0)
+        return ErrorCode::DatabaseCorruptError;
+    else if (strcmp(type, "DatabaseCreateError") == 0)
+        return ErrorCode::DatabaseCreateError;
+    else if (strcmp(type, "DatabaseOpeningError") == 0)
+        return ErrorCode::DatabaseOpeningError;
+    else if (strcmp(type, "DatabaseVersionError") == 0)
+        return ErrorCode::DatabaseVersionError;
+    else if (strcmp(type, "DocNotFoundError") == 0)
+        return ErrorCode::DocNotFoundError;
+    else if (strcmp(type, "FeatureUnavailableError") == 0)
+        return ErrorCode::FeatureUnavailableError;
+    else if (strcmp(type, "InternalError") == 0)
+        return ErrorCode::InternalError;
+    else if (strcmp(type, "NetworkError") == 0)
+        return ErrorCode::NetworkError;
+    else if (strcmp(type, "NetworkTimeoutError") == 0)
+        return ErrorCode::NetworkTimeoutError;
+    else if (strcmp(type, "QueryParserError") == 0)
+        return ErrorCode::QueryParserError;
+    else if (strcmp(type, "RangeError") == 0)
+        return ErrorCode::RangeError;
+    else if (strcmp(type, "SerialisationError") == 0)
+        return ErrorCode::SerialisationError;
+    else if (strcmp(type, "WildcardError") == 0)
+        return ErrorCode::WildcardError;
+    else if (strcmp(type, "DatabaseNotFoundError") == 0)
+        return ErrorCode::DatabaseNotFoundError;
+    else if (strcmp(type, "DatabaseClosedError") == 0)
+        return ErrorCode::DatabaseClosedError;
+
+    return ErrorCode::Unknown;
+}
+
+// Must be called from a catch block, translates the exception in flight
+void set_error(FfiError &err)
+{
+    try
+    {
+        throw;
+    }
+    catch (const Error &ex)
+    {
+        err.code = get_err_code(ex.get_type());
+        err.msg = rust::String::lossy(ex.get_msg());
+        err.context = rust::String::lossy(ex.get_context());
+
+        const char *error_string = ex.get_error_string();
+        if (error_string != NULL)
+            err.error_string = rust::String::lossy(error_string);
+    }
+    catch (const std::exception &ex)
+    {
+        err.code = ErrorCode::CxxException;
+        err.msg = rust::String::lossy(ex.what());
+    }
+    catch (...)
+    {
+        // not an exception class we can get a message from
+        err.code = ErrorCode::Unknown;
+        err.msg = rust::String("unknown C++ exception");
+    }
+}
+
+// Byte slices carry binary data (document data, values, terms) which needn't be UTF-8
+std::string slice_to_string(rust::Slice<const uint8_t> data)
+{
+    return std::string(reinterpret_cast<const char *>(data.data()), data.size());
+}
+
+#ifdef XAPIAN_RUSTY_TEST_THROW
+// Xapian::LogicError and Xapian::RuntimeError can only be thrown through a subclass
+template <typename Base>
+struct TestError : public Base {
+    TestError(const std::string &msg, const char *type) : Base(msg, std::string(), type, static_cast<const char *>(NULL)) { }
+};
+
+// Throws the exception named by `kind` through set_error, for the tests of the error mapping
+void test_throw(rust::Str kind, rust::Str msg, FfiError &err)
+{
+    try
+    {
+        std::string k(kind);
+        std::string m(msg);
+        if (k == "DatabaseModifiedError")
+            throw DatabaseModifiedError(m);
+        else if (k == "DatabaseLockError")
+            throw DatabaseLockError(m);
+        else if (k == "LogicError")
+            throw TestError<LogicError>(m, "LogicError");
+        else if (k == "AssertionError")
+            throw AssertionError(m);
+        else if (k == "InvalidArgumentError")
+            throw InvalidArgumentError(m);
+        else if (k == "InvalidOperationError")
+            throw InvalidOperationError(m);
+        else if (k == "UnimplementedError")
+            throw UnimplementedError(m);
+        else if (k == "RuntimeError")
+            throw TestError<RuntimeError>(m, "RuntimeError");
+        else if (k == "DatabaseError")
+            throw DatabaseError(m);
+        else if (k == "DatabaseCorruptError")
+            throw DatabaseCorruptError(m);
+        else if (k == "DatabaseCreateError")
+            throw DatabaseCreateError(m);
+        else if (k == "DatabaseOpeningError")
+            throw DatabaseOpeningError(m);
+        else if (k == "DatabaseVersionError")
+            throw DatabaseVersionError(m);
+        else if (k == "DocNotFoundError")
+            throw DocNotFoundError(m);
+        else if (k == "FeatureUnavailableError")
+            throw FeatureUnavailableError(m);
+        else if (k == "InternalError")
+            throw InternalError(m);
+        else if (k == "NetworkError")
+            throw NetworkError(m);
+        else if (k == "NetworkTimeoutError")
+            throw NetworkTimeoutError(m);
+        else if (k == "QueryParserError")
+            throw QueryParserError(m);
+        else if (k == "RangeError")
+            throw RangeError(m);
+        else if (k == "SerialisationError")
+            throw SerialisationError(m);
+        else if (k == "WildcardError")
+            throw WildcardError(m);
+        else if (k == "DatabaseNotFoundError")
+            throw DatabaseNotFoundError(m);
+        else if (k == "DatabaseClosedError")
+            throw DatabaseClosedError(m);
+        else if (k == "std::exception")
+            throw std::runtime_error(m);
+        else if (k == "int")
+            throw 42;
+        else
+            // a Xapian error class newer than the table in get_err_code
+            throw TestError<RuntimeError>(m, "FutureError");
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+#endif
+
+std::unique_ptr<Database> new_database(FfiError &err)
+{
+    try
+    {
         return std::make_unique<Xapian::Database>();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
         return NULL;
     }
 }
 
-std::unique_ptr<Database> new_database_with_path(rust::Str path, int8_t db_type, int8_t &err)
+std::unique_ptr<Database> new_database_with_path(rust::Str path, int32_t flags, FfiError &err)
 {
     try
     {
-        err = 0;
-
-        if (db_type == CHERT)
-            return std::make_unique<Database>(Chert::open(std::string(path)));
-        else if (db_type == IN_MEMORY)
-            return std::make_unique<Database>(InMemory::open());
-        else
-            return std::make_unique<Database>(std::string(path));
+        return std::make_unique<Database>(std::string(path), flags);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
         return NULL;
     }
 }
 
-void add_database(Database &db, Database &add_db, int8_t &err)
+void add_database(Database &db, Database &add_db, FfiError &err)
 {
     try
     {
         db.add_database(add_db);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
     }
 }
 
-void database_close(Database &db, int8_t &err)
+void database_close(Database &db, FfiError &err)
 {
     try
     {
         db.close();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
     }
 }
 
-void database_reopen(Database &db, int8_t &err)
+void database_reopen(Database &db, FfiError &err)
 {
     try
     {
         db.reopen();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
     }
 }
 
-std::unique_ptr<Enquire> new_enquire(Database &db, int8_t &err)
+doccount database_get_doccount(const Database &db, FfiError &err)
 {
     try
     {
-        err = 0;
-        return std::make_unique<Xapian::Enquire>(db);
+        return db.get_doccount();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return NULL;
+        set_error(err);
+        return 0;
     }
 }
 
+docid database_get_lastdocid(const Database &db, FfiError &err)
+{
+    try
+    {
+        return db.get_lastdocid();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
 
-//////
+double database_get_avlength(const Database &db, FfiError &err)
+{
+    try
+    {
+        return db.get_avlength();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
 
-std::unique_ptr<Stem> new_stem(rust::Str lang, int8_t &err)
+totallength database_get_total_length(const Database &db, FfiError &err)
 {
     try
     {
-        err = 0;
-        return std::make_unique<Stem>(std::string(lang));
+        return db.get_total_length();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return NULL;
+        set_error(err);
+        return 0;
     }
 }
 
-///////////////////////////////////////////////////////////////
-std::unique_ptr<WritableDatabase> new_writable_database_with_path(rust::Str path, int8_t action, int8_t db_type, int8_t &err)
+termcount database_get_doclength(const Database &db, docid did, FfiError &err)
 {
     try
     {
-        err = 0;
+        return db.get_doclength(did);
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
 
-        if (db_type == CHERT) {
-            return std::make_unique<WritableDatabase>(Chert::open(std::string(path), action));
-        }
-        else if (db_type == IN_MEMORY)
-            return std::make_unique<WritableDatabase>(InMemory::open());
-        else {
-            return std::make_unique<WritableDatabase>(std::string(path), action);
-        }
+termcount database_get_unique_terms(const Database &db, docid did, FfiError &err)
+{
+    try
+    {
+        return db.get_unique_terms(did);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        if (err == 0) {
-            err = -4;
-        }
-        return NULL;
+        set_error(err);
+        return 0;
     }
 }
 
-void commit(WritableDatabase &db, int8_t &err)
+doccount database_get_termfreq(const Database &db, rust::Slice<const uint8_t> term, FfiError &err)
 {
     try
     {
-        db.commit();
+        return db.get_termfreq(slice_to_string(term));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return 0;
     }
 }
 
-int32_t get_doccount (WritableDatabase &db, int8_t &err) {
+termcount database_get_collection_freq(const Database &db, rust::Slice<const uint8_t> term, FfiError &err)
+{
     try
     {
-        return db.get_doccount();
+        return db.get_collection_freq(slice_to_string(term));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
         return 0;
     }
 }
 
-docid replace_document(WritableDatabase &db, rust::Str unique_term, Document &doc, int8_t &err)
+bool database_term_exists(const Database &db, rust::Slice<const uint8_t> term, FfiError &err)
 {
     try
     {
-        return db.replace_document(std::string(unique_term), doc);
+        return db.term_exists(slice_to_string(term));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return -1;
+        set_error(err);
+        return false;
     }
 }
 
-void delete_document(WritableDatabase &db, rust::Str unique_term, int8_t &err)
+termcount database_get_doclength_lower_bound(const Database &db, FfiError &err)
 {
     try
     {
-        db.delete_document(std::string(unique_term));
+        return db.get_doclength_lower_bound();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return 0;
     }
 }
 
+termcount database_get_doclength_upper_bound(const Database &db, FfiError &err)
+{
+    try
+    {
+        return db.get_doclength_upper_bound();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
 
-////////////////////////////////////////////////////////////////
+termcount database_get_wdf_upper_bound(const Database &db, rust::Slice<const uint8_t> term, FfiError &err)
+{
+    try
+    {
+        return db.get_wdf_upper_bound(slice_to_string(term));
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
 
-std::unique_ptr<TermGenerator> new_termgenerator(int8_t &err)
+std::unique_ptr<TermIter> database_allterms(const Database &db, rust::Slice<const uint8_t> prefix, FfiError &err)
 {
     try
     {
-        err = 0;
-        return std::make_unique<TermGenerator>();
+        std::string s_prefix = slice_to_string(prefix);
+        return std::unique_ptr<TermIter>(new TermIter { db.allterms_begin(s_prefix), db.allterms_end(s_prefix) });
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
         return NULL;
     }
 }
 
-void set_stemmer(TermGenerator &tg, Stem &stem, int8_t &err)
+std::unique_ptr<PostingIter> database_postlist(const Database &db, rust::Slice<const uint8_t> term, FfiError &err)
 {
     try
     {
-        tg.set_stemmer(stem);
+        std::string s_term = slice_to_string(term);
+        return std::unique_ptr<PostingIter>(new PostingIter { db.postlist_begin(s_term), db.postlist_end(s_term) });
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return NULL;
     }
 }
 
-void set_document(TermGenerator &tg, Document &doc, int8_t &err)
+std::unique_ptr<PositionIter> database_positionlist(const Database &db, docid did, rust::Slice<const uint8_t> term, FfiError &err)
 {
     try
     {
-        err = 0;
-        tg.set_document(doc);
+        std::string s_term = slice_to_string(term);
+        return std::unique_ptr<PositionIter>(new PositionIter { db.positionlist_begin(did, s_term), db.positionlist_end(did, s_term) });
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return NULL;
     }
 }
 
-void index_text(TermGenerator &tg, rust::Str data, int8_t &err)
+std::unique_ptr<Document> database_get_document(const Database &db, docid did, bool assume_valid, FfiError &err)
 {
     try
     {
-        err = 0;
-        tg.index_text(std::string(data));
+        return std::make_unique<Document>(db.get_document(did, assume_valid ? DOC_ASSUME_VALID : 0));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return NULL;
     }
 }
 
-void index_text_with_prefix(TermGenerator &tg, rust::Str data, rust::Str prefix, int8_t &err)
+std::unique_ptr<ValueIter> database_valuestream(const Database &db, valueno slot, FfiError &err)
 {
     try
     {
-        err = 0;
-        tg.index_text(std::string(data), 1, std::string(prefix));
+        return std::unique_ptr<ValueIter>(new ValueIter { db.valuestream_begin(slot), db.valuestream_end(slot) });
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return NULL;
     }
 }
 
-void index_int(TermGenerator &tg, int32_t in_data, rust::Str prefix, int8_t &err)
+doccount database_get_value_freq(const Database &db, valueno slot, FfiError &err)
 {
     try
     {
-        err = 0;
-        std::string data = sortable_serialise(in_data);
-        tg.index_text(data, 1, std::string(prefix));
+        return db.get_value_freq(slot);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return 0;
     }
 }
 
-void index_long(TermGenerator &tg, int64_t in_data, rust::Str prefix, int8_t &err)
+std::unique_ptr<std::string> database_get_value_lower_bound(const Database &db, valueno slot, FfiError &err)
 {
     try
     {
-        err = 0;
-        std::string data = sortable_serialise(in_data);
-        tg.index_text(data, 1, std::string(prefix));
+        return std::make_unique<std::string>(db.get_value_lower_bound(slot));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return NULL;
     }
 }
 
-void index_float(TermGenerator &tg, float in_data, rust::Str prefix, int8_t &err)
+std::unique_ptr<std::string> database_get_value_upper_bound(const Database &db, valueno slot, FfiError &err)
 {
     try
     {
-        err = 0;
-        std::string data = sortable_serialise(in_data);
-        tg.index_text(data, 1, std::string(prefix));
+        return std::make_unique<std::string>(db.get_value_upper_bound(slot));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return NULL;
     }
 }
 
-void index_double(TermGenerator &tg, double in_data, rust::Str prefix, int8_t &err)
+std::unique_ptr<Enquire> new_enquire(Database &db, FfiError &err)
 {
     try
     {
-        err = 0;
-        std::string data = sortable_serialise(in_data);
-        tg.index_text(data, 1, std::string(prefix));
+        return std::make_unique<Xapian::Enquire>(db);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return NULL;
     }
 }
 
-////////////////////////////////////////////////////////////////
 
-std::unique_ptr<Document> new_document(int8_t &err)
+//////
+
+std::unique_ptr<Stem> new_stem(rust::Str lang, FfiError &err)
 {
     try
     {
-        err = 0;
-        return std::make_unique<Document>();
+        return std::make_unique<Stem>(std::string(lang));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
         return NULL;
     }
 }
 
-void add_string(Document &doc, valueno slot, rust::Str data, int8_t &err)
+///////////////////////////////////////////////////////////////
+std::unique_ptr<WritableDatabase> new_writable_database_with_flags(rust::Str path, int32_t flags, FfiError &err)
 {
     try
     {
-        err = 0;
-        doc.add_value(slot, std::string(data));
+        return std::make_unique<WritableDatabase>(std::string(path), flags);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return NULL;
     }
 }
 
-void add_int(Document &doc, valueno slot, int in_data, int8_t &err)
+void commit(WritableDatabase &db, FfiError &err)
 {
     try
     {
-        err = 0;
-        std::string data = sortable_serialise(in_data);
-        doc.add_value(slot, data);
+        db.commit();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
     }
 }
 
-void add_long(Document &doc, valueno slot, int64_t in_data, int8_t &err)
-{
+doccount get_doccount (WritableDatabase &db, FfiError &err) {
     try
     {
-        err = 0;
-        std::string data = sortable_serialise(in_data);
-        doc.add_value(slot, data);
+        return db.get_doccount();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return 0;
     }
 }
 
-void add_float(Document &doc, valueno slot, float in_data, int8_t &err)
+std::unique_ptr<Document> writable_database_get_document(const WritableDatabase &db, docid did, bool assume_valid, FfiError &err)
+{
+    return database_get_document(db, did, assume_valid, err);
+}
+
+std::unique_ptr<Database> writable_database_as_database(const WritableDatabase &db, FfiError &err)
 {
     try
     {
-        err = 0;
-        std::string data = sortable_serialise(in_data);
-        doc.add_value(slot, data);
+        // copies share the writer's internals, so uncommitted changes are visible
+        return std::make_unique<Database>(db);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return NULL;
     }
 }
 
-void add_double(Document &doc, valueno slot, double in_data, int8_t &err)
+docid replace_document(WritableDatabase &db, rust::Slice<const uint8_t> unique_term, Document &doc, FfiError &err)
 {
     try
     {
-        err = 0;
-        std::string data = sortable_serialise(in_data);
-        doc.add_value(slot, data);
+        return db.replace_document(slice_to_string(unique_term), doc);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return -1;
     }
 }
 
-void set_data(Document &doc, rust::Str data, int8_t &err)
+void delete_document(WritableDatabase &db, rust::Slice<const uint8_t> unique_term, FfiError &err)
 {
     try
     {
-        err = 0;
-        doc.set_data(std::string(data));
+        db.delete_document(slice_to_string(unique_term));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
     }
 }
 
-void add_boolean_term(Document &doc, rust::Str data, int8_t &err)
+docid add_document(WritableDatabase &db, Document &doc, FfiError &err)
 {
     try
     {
-        err = 0;
-        doc.add_boolean_term(std::string(data));
+        return db.add_document(doc);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+        return 0;
     }
 }
 
-std::string g_str_0;
-const std::string &get_doc_data (Document &doc) {
+void replace_document_by_id(WritableDatabase &db, docid did, Document &doc, FfiError &err)
+{
     try
     {
-        //err = 0;
-        g_str_0 = doc.get_data();
+        db.replace_document(did, doc);
     }
-    catch (Error ex)
+    catch (...)
     {
-        //err = get_err_code(ex.get_type());
-        g_str_0 = std::string("");
+        set_error(err);
     }
-
-    return g_str_0;
 }
 
-//////
-
-std::unique_ptr<QueryParser> new_query_parser(int8_t &err)
+void delete_document_by_id(WritableDatabase &db, docid did, FfiError &err)
 {
     try
     {
-        err = 0;
-        return std::make_unique<Xapian::QueryParser>();
+        db.delete_document(did);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return NULL;
+        set_error(err);
     }
 }
 
-void set_max_wildcard_expansion(QueryParser &qp, int32_t limit, int8_t &err) {
+void begin_transaction(WritableDatabase &db, bool flushed, FfiError &err)
+{
     try
     {
-        err = 0;
-        qp.set_max_expansion (limit, Query::WILDCARD_LIMIT_MOST_FREQUENT, QueryParser::FLAG_WILDCARD);
+        db.begin_transaction(flushed);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return;
+        set_error(err);
     }
 }
 
-void set_stemmer_to_qp(QueryParser &qp, Stem &stem, int8_t &err) {
+void commit_transaction(WritableDatabase &db, FfiError &err)
+{
     try
     {
-        err = 0;
-        qp.set_stemmer(stem);
+        db.commit_transaction();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
     }
 }
 
-void set_database(QueryParser &qp, Database &db, int8_t &err)
+void cancel_transaction(WritableDatabase &db, FfiError &err)
 {
     try
     {
-        err = 0;
-        qp.set_database(db);
+        db.cancel_transaction();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
     }
 }
 
-std::unique_ptr<Query> parse_query(QueryParser &qp, rust::Str data, int16_t flags, int8_t &err) {
+
+////////////////////////////////////////////////////////////////
+
+std::unique_ptr<TermGenerator> new_termgenerator(FfiError &err)
+{
     try
     {
-        err = 0;
-        return std::make_unique<Xapian::Query>(qp.parse_query(std::string(data), flags));
+        return std::make_unique<TermGenerator>();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
         return NULL;
     }
 }
 
-std::unique_ptr<Query> parse_query_with_prefix(QueryParser &qp, rust::Str query, int16_t flags, rust::Str prefix, int8_t &err) {
+void set_stemmer(TermGenerator &tg, Stem &stem, FfiError &err)
+{
     try
     {
-        err = 0;
-        return std::make_unique<Xapian::Query>(qp.parse_query(std::string(query), flags, std::string(prefix)));
+        tg.set_stemmer(stem);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return NULL;
+        set_error(err);
     }
 }
 
-////////
+void set_document(TermGenerator &tg, Document &doc, FfiError &err)
+{
+    try
+    {
+        tg.set_document(doc);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
 
-std::unique_ptr<Query> new_query(int8_t &err) {
+void index_text(TermGenerator &tg, rust::Str data, FfiError &err)
+{
     try
     {
-        err = 0;
-        return std::make_unique<Xapian::Query>();
+        tg.index_text(std::string(data));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return NULL;
+        set_error(err);
     }
 }
 
-std::unique_ptr<Query> new_query_range(int32_t _op, valueno slot, double _begin, double _end, int8_t &err) {
+void index_text_with_prefix(TermGenerator &tg, rust::Str data, rust::Str prefix, FfiError &err)
+{
     try
     {
-        err = 0;
+        tg.index_text(std::string(data), 1, std::string(prefix));
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
 
-        std::string s_begin = Xapian::sortable_serialise(_begin);
-        std::string s_end = Xapian::sortable_serialise(_end);
-        Xapian::Query _query ((Xapian::Query::op)_op, slot, s_begin, s_end);
+void index_int(TermGenerator &tg, int32_t in_data, rust::Str prefix, FfiError &err)
+{
+    try
+    {
+        std::string data = sortable_serialise(in_data);
+        tg.index_text(data, 1, std::string(prefix));
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
 
-        return std::make_unique<Xapian::Query>(_query);
+void index_long(TermGenerator &tg, int64_t in_data, rust::Str prefix, FfiError &err)
+{
+    try
+    {
+        std::string data = sortable_serialise(in_data);
+        tg.index_text(data, 1, std::string(prefix));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return NULL;
+        set_error(err);
     }
 }
 
-std::unique_ptr<Query> add_right_query(Query &this_q, int32_t _op, Query &q, int8_t &err) {
+void index_float(TermGenerator &tg, float in_data, rust::Str prefix, FfiError &err)
+{
     try
     {
-        err = 0;
-        return std::make_unique<Xapian::Query>((Xapian::Query::op)_op, this_q, q);
+        std::string data = sortable_serialise(in_data);
+        tg.index_text(data, 1, std::string(prefix));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return NULL;
+        set_error(err);
     }
 }
 
-std::unique_ptr<Query> new_query_double_with_prefix(rust::Str prefix, double _d, int8_t &err) {
+void index_double(TermGenerator &tg, double in_data, rust::Str prefix, FfiError &err)
+{
     try
     {
-        err = 0;
+        std::string data = sortable_serialise(in_data);
+        tg.index_text(data, 1, std::string(prefix));
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
 
-        std::string s = std::string(prefix) + Xapian::sortable_serialise(_d);
+////////////////////////////////////////////////////////////////
 
-        Xapian::Query _query (s);
-        return std::make_unique<Xapian::Query>(_query);
+std::unique_ptr<Document> new_document(FfiError &err)
+{
+    try
+    {
+        return std::make_unique<Document>();
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
         return NULL;
     }
 }
 
-bool query_is_empty (Query &q, int8_t &err) {
+void add_string(Document &doc, valueno slot, rust::Slice<const uint8_t> data, FfiError &err)
+{
     try
     {
-        err = 0;
-        return q.empty();
+        doc.add_value(slot, slice_to_string(data));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return true;
+        set_error(err);
     }
 }
 
-std::string g_str_1;
-const std::string &get_description (Query &q) {
+void add_int(Document &doc, valueno slot, int in_data, FfiError &err)
+{
     try
     {
-        //err = 0;
-        g_str_1 = q.get_description();
-        return g_str_1;
+        std::string data = sortable_serialise(in_data);
+        doc.add_value(slot, data);
     }
-    catch (Error ex)
+    catch (...)
     {
-        //err = get_err_code(ex.get_type());
-        return NULL;
+        set_error(err);
     }
 }
 
-////
-
-std::unique_ptr<MSet> get_mset(Enquire &en, int32_t from, int32_t size, int8_t &err) {
+void add_long(Document &doc, valueno slot, int64_t in_data, FfiError &err)
+{
     try
     {
-        err = 0;
-        return std::make_unique<Xapian::MSet>(en.get_mset(from, size));
+        std::string data = sortable_serialise(in_data);
+        doc.add_value(slot, data);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return NULL;
+        set_error(err);
     }
 }
 
-void set_query(Enquire &en, Query &query, int8_t &err) {
+void add_float(Document &doc, valueno slot, float in_data, FfiError &err)
+{
     try
     {
-        err = 0;
-        en.set_query(query);
+        std::string data = sortable_serialise(in_data);
+        doc.add_value(slot, data);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
     }
 }
 
-void set_sort_by_key(Enquire &en, MultiValueKeyMaker &sorter, bool reverse, int8_t &err) {
+void add_double(Document &doc, valueno slot, double in_data, FfiError &err)
+{
     try
     {
-        err = 0;
-        en.set_sort_by_key(&sorter, reverse);
+        std::string data = sortable_serialise(in_data);
+        doc.add_value(slot, data);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
     }
 }
 
-/////
-
-int get_matches_estimated (MSet &set, int8_t &err) {
+void set_data(Document &doc, rust::Slice<const uint8_t> data, FfiError &err)
+{
     try
     {
-        err = 0;
-        return set.get_matches_estimated();
+        doc.set_data(slice_to_string(data));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return -1;
+        set_error(err);
     }
 }
 
-int mset_size (MSet &set, int8_t &err) {
+void add_boolean_term(Document &doc, rust::Slice<const uint8_t> data, FfiError &err)
+{
     try
     {
-        err = 0;
-        return set.size();
+        doc.add_boolean_term(slice_to_string(data));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return -1;
+        set_error(err);
     }
 }
 
-std::unique_ptr<Document> get_doc_by_index (MSet &set, int32_t index, int8_t &err) {
+void add_term(Document &doc, rust::Slice<const uint8_t> term, termcount wdf_inc, FfiError &err)
+{
     try
     {
-        err = 0;
-        return std::make_unique<Xapian::Document>(set.get_doc_by_index(index));
+        doc.add_term(slice_to_string(term), wdf_inc);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return NULL;
+        set_error(err);
     }
 }
 
-/////
-
-std::unique_ptr<MultiValueKeyMaker> new_multi_value_key_maker (int8_t &err) {
+void add_posting(Document &doc, rust::Slice<const uint8_t> term, termpos pos, termcount wdf_inc, FfiError &err)
+{
     try
     {
-        err = 0;
-        return std::make_unique<Xapian::MultiValueKeyMaker>();
+        doc.add_posting(slice_to_string(term), pos, wdf_inc);
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
-        return NULL;
+        set_error(err);
     }
 }
 
-void add_value_to_multi_value_key_maker(MultiValueKeyMaker &this_m, valueno slot, bool asc_desc, int8_t &err) {
+void remove_term(Document &doc, rust::Slice<const uint8_t> term, FfiError &err)
+{
     try
     {
-        err = 0;
-        this_m.add_value(slot, asc_desc);
+        doc.remove_term(slice_to_string(term));
     }
-    catch (Error ex)
+    catch (...)
     {
-        err = get_err_code(ex.get_type());
+        set_error(err);
+    }
+}
+
+void remove_posting(Document &doc, rust::Slice<const uint8_t> term, termpos pos, termcount wdf_dec, FfiError &err)
+{
+    try
+    {
+        doc.remove_posting(slice_to_string(term), pos, wdf_dec);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+termpos remove_postings(Document &doc, rust::Slice<const uint8_t> term, termpos first, termpos last, termcount wdf_dec, FfiError &err)
+{
+    try
+    {
+        return doc.remove_postings(slice_to_string(term), first, last, wdf_dec);
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+void clear_terms(Document &doc, FfiError &err)
+{
+    try
+    {
+        doc.clear_terms();
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void remove_value(Document &doc, valueno slot, FfiError &err)
+{
+    try
+    {
+        doc.remove_value(slot);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void clear_values(Document &doc, FfiError &err)
+{
+    try
+    {
+        doc.clear_values();
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+std::unique_ptr<std::string> get_doc_data (const Document &doc, FfiError &err) {
+    try
+    {
+        return std::make_unique<std::string>(doc.get_data());
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+std::unique_ptr<std::string> get_value (const Document &doc, valueno slot, FfiError &err) {
+    try
+    {
+        return std::make_unique<std::string>(doc.get_value(slot));
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+docid get_docid (const Document &doc, FfiError &err) {
+    try
+    {
+        return doc.get_docid();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+termcount termlist_count (const Document &doc, FfiError &err) {
+    try
+    {
+        return doc.termlist_count();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+termcount values_count (const Document &doc, FfiError &err) {
+    try
+    {
+        return doc.values_count();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+std::unique_ptr<TermIter> document_termlist (const Document &doc, FfiError &err) {
+    try
+    {
+        return std::unique_ptr<TermIter>(new TermIter { doc.termlist_begin(), doc.termlist_end() });
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+std::unique_ptr<ValueIter> document_values (const Document &doc, FfiError &err) {
+    try
+    {
+        return std::unique_ptr<ValueIter>(new ValueIter { doc.values_begin(), doc.values_end() });
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+//////
+
+bool term_iter_at_end (TermIter &iter) {
+    return iter.it == iter.end;
+}
+
+void term_iter_next (TermIter &iter, FfiError &err) {
+    try
+    {
+        ++iter.it;
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+std::unique_ptr<std::string> term_iter_term (TermIter &iter, FfiError &err) {
+    try
+    {
+        return std::make_unique<std::string>(*iter.it);
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+termcount term_iter_wdf (TermIter &iter, FfiError &err) {
+    try
+    {
+        return iter.it.get_wdf();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+doccount term_iter_termfreq (TermIter &iter, FfiError &err) {
+    try
+    {
+        return iter.it.get_termfreq();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+rust::Vec<termpos> term_iter_positions (TermIter &iter, FfiError &err) {
+    rust::Vec<termpos> positions;
+    try
+    {
+        for (PositionIterator pos = iter.it.positionlist_begin(); pos != iter.it.positionlist_end(); ++pos)
+            positions.push_back(*pos);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+
+    return positions;
+}
+
+//////
+
+bool value_iter_at_end (ValueIter &iter) {
+    return iter.it == iter.end;
+}
+
+void value_iter_next (ValueIter &iter, FfiError &err) {
+    try
+    {
+        ++iter.it;
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+valueno value_iter_slot (ValueIter &iter, FfiError &err) {
+    try
+    {
+        return iter.it.get_valueno();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return BAD_VALUENO;
+    }
+}
+
+std::unique_ptr<std::string> value_iter_value (ValueIter &iter, FfiError &err) {
+    try
+    {
+        return std::make_unique<std::string>(*iter.it);
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+//////
+
+docid value_iter_docid (ValueIter &iter, FfiError &err) {
+    try
+    {
+        return iter.it.get_docid();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+void value_iter_skip_to (ValueIter &iter, docid did, FfiError &err) {
+    try
+    {
+        if (iter.it != iter.end)
+            iter.it.skip_to(did);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+bool value_iter_check (ValueIter &iter, docid did, FfiError &err) {
+    try
+    {
+        if (iter.it == iter.end)
+            return false;
+        if (iter.it.check(did))
+            // check() may have skipped past a document without a value
+            return iter.it != iter.end && iter.it.get_docid() == did;
+        // the position is unspecified until moved to the first entry after did
+        ++iter.it;
+        return false;
+    }
+    catch (...)
+    {
+        set_error(err);
+        return false;
+    }
+}
+
+bool posting_iter_at_end (PostingIter &iter) {
+    return iter.it == iter.end;
+}
+
+void posting_iter_next (PostingIter &iter, FfiError &err) {
+    try
+    {
+        ++iter.it;
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void posting_iter_skip_to (PostingIter &iter, docid did, FfiError &err) {
+    try
+    {
+        if (iter.it != iter.end)
+            iter.it.skip_to(did);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+docid posting_iter_docid (PostingIter &iter, FfiError &err) {
+    try
+    {
+        return *iter.it;
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+termcount posting_iter_wdf (PostingIter &iter, FfiError &err) {
+    try
+    {
+        return iter.it.get_wdf();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+termcount posting_iter_doclength (PostingIter &iter, FfiError &err) {
+    try
+    {
+        return iter.it.get_doclength();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+bool position_iter_at_end (PositionIter &iter) {
+    return iter.it == iter.end;
+}
+
+void position_iter_next (PositionIter &iter, FfiError &err) {
+    try
+    {
+        ++iter.it;
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void position_iter_skip_to (PositionIter &iter, termpos pos, FfiError &err) {
+    try
+    {
+        if (iter.it != iter.end)
+            iter.it.skip_to(pos);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+termpos position_iter_pos (PositionIter &iter, FfiError &err) {
+    try
+    {
+        return *iter.it;
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+std::unique_ptr<std::string> sortable_serialise_double (double value, FfiError &err) {
+    try
+    {
+        return std::make_unique<std::string>(sortable_serialise(value));
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+double sortable_unserialise_bytes (rust::Slice<const uint8_t> serialised, FfiError &err) {
+    try
+    {
+        return sortable_unserialise(slice_to_string(serialised));
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+//////
+
+std::unique_ptr<QueryParser> new_query_parser(FfiError &err)
+{
+    try
+    {
+        return std::make_unique<Xapian::QueryParser>();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+void set_max_wildcard_expansion(QueryParser &qp, int32_t limit, FfiError &err) {
+    try
+    {
+        qp.set_max_expansion (limit, Query::WILDCARD_LIMIT_MOST_FREQUENT, QueryParser::FLAG_WILDCARD);
+    }
+    catch (...)
+    {
+        set_error(err);
+        return;
+    }
+}
+
+void set_stemmer_to_qp(QueryParser &qp, Stem &stem, FfiError &err) {
+    try
+    {
+        qp.set_stemmer(stem);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void set_database(QueryParser &qp, Database &db, FfiError &err)
+{
+    try
+    {
+        qp.set_database(db);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+std::unique_ptr<Query> parse_query(QueryParser &qp, rust::Str data, int16_t flags, FfiError &err) {
+    try
+    {
+        return std::make_unique<Xapian::Query>(qp.parse_query(std::string(data), flags));
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+std::unique_ptr<Query> parse_query_with_prefix(QueryParser &qp, rust::Str query, int16_t flags, rust::Str prefix, FfiError &err) {
+    try
+    {
+        return std::make_unique<Xapian::Query>(qp.parse_query(std::string(query), flags, std::string(prefix)));
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+////////
+
+std::unique_ptr<Query> new_query_range(int32_t _op, valueno slot, double _begin, double _end, FfiError &err) {
+    try
+    {
+        std::string s_begin = Xapian::sortable_serialise(_begin);
+        std::string s_end = Xapian::sortable_serialise(_end);
+        Xapian::Query _query ((Xapian::Query::op)_op, slot, s_begin, s_end);
+
+        return std::make_unique<Xapian::Query>(_query);
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+std::unique_ptr<Query> add_right_query(Query &this_q, int32_t _op, Query &q, FfiError &err) {
+    try
+    {
+        return std::make_unique<Xapian::Query>((Xapian::Query::op)_op, this_q, q);
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+std::unique_ptr<Query> new_query_double_with_prefix(rust::Str prefix, double _d, FfiError &err) {
+    try
+    {
+        std::string s = std::string(prefix) + Xapian::sortable_serialise(_d);
+
+        Xapian::Query _query (s);
+        return std::make_unique<Xapian::Query>(_query);
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+bool query_is_empty (Query &q, FfiError &err) {
+    try
+    {
+        return q.empty();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return true;
+    }
+}
+
+std::unique_ptr<std::string> get_description (Query &q, FfiError &err) {
+    try
+    {
+        return std::make_unique<std::string>(q.get_description());
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+////
+
+std::unique_ptr<MSet> get_mset(Enquire &en, int32_t from, int32_t size, FfiError &err) {
+    try
+    {
+        return std::make_unique<Xapian::MSet>(en.get_mset(from, size));
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+void set_query(Enquire &en, Query &query, FfiError &err) {
+    try
+    {
+        en.set_query(query);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void set_sort_by_key(Enquire &en, KeyMaker &sorter, bool reverse, FfiError &err) {
+    try
+    {
+        en.set_sort_by_key(&sorter, reverse);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void set_sort_by_key_then_relevance(Enquire &en, KeyMaker &sorter, bool reverse, FfiError &err) {
+    try
+    {
+        en.set_sort_by_key_then_relevance(&sorter, reverse);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void set_sort_by_relevance_then_key(Enquire &en, KeyMaker &sorter, bool reverse, FfiError &err) {
+    try
+    {
+        en.set_sort_by_relevance_then_key(&sorter, reverse);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void set_sort_by_value(Enquire &en, valueno slot, bool reverse, FfiError &err) {
+    try
+    {
+        en.set_sort_by_value(slot, reverse);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void set_sort_by_value_then_relevance(Enquire &en, valueno slot, bool reverse, FfiError &err) {
+    try
+    {
+        en.set_sort_by_value_then_relevance(slot, reverse);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void set_sort_by_relevance_then_value(Enquire &en, valueno slot, bool reverse, FfiError &err) {
+    try
+    {
+        en.set_sort_by_relevance_then_value(slot, reverse);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void set_sort_by_relevance(Enquire &en, FfiError &err) {
+    try
+    {
+        en.set_sort_by_relevance();
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void set_docid_order(Enquire &en, int32_t order, FfiError &err) {
+    try
+    {
+        en.set_docid_order(Enquire::docid_order(order));
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void set_collapse_key(Enquire &en, valueno slot, doccount collapse_max, FfiError &err) {
+    try
+    {
+        en.set_collapse_key(slot, collapse_max);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+// Enquire keeps its own clone of the weighting scheme
+template <typename W, typename... Args>
+static void set_weighting(Enquire &en, FfiError &err, Args... args) {
+    try
+    {
+        en.set_weighting_scheme(W(args...));
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+void set_weighting_bool(Enquire &en, FfiError &err) {
+    set_weighting<BoolWeight>(en, err);
+}
+
+void set_weighting_coord(Enquire &en, FfiError &err) {
+    set_weighting<CoordWeight>(en, err);
+}
+
+void set_weighting_tfidf(Enquire &en, rust::Str normalizations, FfiError &err) {
+    set_weighting<TfIdfWeight>(en, err, std::string(normalizations));
+}
+
+void set_weighting_bm25(Enquire &en, double k1, double k2, double k3, double b, double min_normlen, FfiError &err) {
+    set_weighting<BM25Weight>(en, err, k1, k2, k3, b, min_normlen);
+}
+
+void set_weighting_bm25plus(Enquire &en, double k1, double k2, double k3, double b, double min_normlen, double delta, FfiError &err) {
+    set_weighting<BM25PlusWeight>(en, err, k1, k2, k3, b, min_normlen, delta);
+}
+
+void set_weighting_trad(Enquire &en, double k, FfiError &err) {
+    set_weighting<TradWeight>(en, err, k);
+}
+
+void set_weighting_inl2(Enquire &en, double c, FfiError &err) {
+    set_weighting<InL2Weight>(en, err, c);
+}
+
+void set_weighting_ifb2(Enquire &en, double c, FfiError &err) {
+    set_weighting<IfB2Weight>(en, err, c);
+}
+
+void set_weighting_ineb2(Enquire &en, double c, FfiError &err) {
+    set_weighting<IneB2Weight>(en, err, c);
+}
+
+void set_weighting_bb2(Enquire &en, double c, FfiError &err) {
+    set_weighting<BB2Weight>(en, err, c);
+}
+
+void set_weighting_dlh(Enquire &en, FfiError &err) {
+    set_weighting<DLHWeight>(en, err);
+}
+
+void set_weighting_pl2(Enquire &en, double c, FfiError &err) {
+    set_weighting<PL2Weight>(en, err, c);
+}
+
+void set_weighting_pl2plus(Enquire &en, double c, double delta, FfiError &err) {
+    set_weighting<PL2PlusWeight>(en, err, c, delta);
+}
+
+void set_weighting_dph(Enquire &en, FfiError &err) {
+    set_weighting<DPHWeight>(en, err);
+}
+
+void set_weighting_lm(Enquire &en, double param_log, int32_t smoothing, double param_smoothing1, double param_smoothing2, FfiError &err) {
+    set_weighting<LMWeight>(en, err, param_log, Weight::type_smoothing(smoothing), param_smoothing1, param_smoothing2);
+}
+
+// Weight subclass forwarding to a Rust implementation of the Weight trait
+class RustWeight : public Weight {
+    rust::Box<WeightBox> inner;
+    stat_flags flags;
+
+  public:
+    explicit RustWeight(rust::Box<WeightBox> inner_)
+        : inner(std::move(inner_)), flags(stat_flags(weight_stats_needed(*inner)))
+    {
+        need_stat(flags);
+    }
+
+    RustWeight * clone() const {
+        return new RustWeight(weight_clone(*inner));
+    }
+
+    void init(double factor) {
+        // only the statistics asked for are set up by the matcher
+        FfiWeightStats stats {};
+        stats.collection_size = get_collection_size();
+        stats.rset_size = get_rset_size();
+        if (flags & AVERAGE_LENGTH)
+            stats.average_length = get_average_length();
+        if (flags & TERMFREQ)
+            stats.termfreq = get_termfreq();
+        if (flags & RELTERMFREQ)
+            stats.reltermfreq = get_reltermfreq();
+        if (flags & COLLECTION_FREQ)
+            stats.collection_freq = get_collection_freq();
+        if (flags & QUERY_LENGTH)
+            stats.query_length = get_query_length();
+        if (flags & WQF)
+            stats.wqf = get_wqf();
+        if (flags & DOC_LENGTH_MIN)
+            stats.doclength_lower_bound = get_doclength_lower_bound();
+        if (flags & DOC_LENGTH_MAX)
+            stats.doclength_upper_bound = get_doclength_upper_bound();
+        if (flags & WDF_MAX)
+            stats.wdf_upper_bound = get_wdf_upper_bound();
+        if ((flags & TOTAL_LENGTH) == TOTAL_LENGTH)
+            stats.total_length = get_total_length();
+        weight_init(*inner, factor, stats);
+    }
+
+    double get_sumpart(termcount wdf, termcount doclen, termcount uniqterms) const {
+        return weight_get_sumpart(*inner, wdf, doclen, uniqterms);
+    }
+
+    double get_maxpart() const {
+        return weight_get_maxpart(*inner);
+    }
+
+    double get_sumextra(termcount doclen, termcount uniqterms) const {
+        return weight_get_sumextra(*inner, doclen, uniqterms);
+    }
+
+    double get_maxextra() const {
+        return weight_get_maxextra(*inner);
+    }
+};
+
+void set_weighting_rust(Enquire &en, rust::Box<WeightBox> weight, FfiError &err) {
+    try
+    {
+        RustWeight w(std::move(weight));
+        en.set_weighting_scheme(w);
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+/////
+
+int get_matches_estimated (const MSet &set, FfiError &err) {
+    try
+    {
+        return set.get_matches_estimated();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return -1;
+    }
+}
+
+doccount mset_size (const MSet &set, FfiError &err) {
+    try
+    {
+        return set.size();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return -1;
+    }
+}
+
+std::unique_ptr<Document> get_doc_by_index (const MSet &set, doccount index, FfiError &err) {
+    try
+    {
+        return std::make_unique<Xapian::Document>(set[index].get_document());
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+doccount get_matches_lower_bound (const MSet &set, FfiError &err) {
+    try
+    {
+        return set.get_matches_lower_bound();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+doccount get_matches_upper_bound (const MSet &set, FfiError &err) {
+    try
+    {
+        return set.get_matches_upper_bound();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+double get_max_possible (const MSet &set, FfiError &err) {
+    try
+    {
+        return set.get_max_possible();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+double get_max_attained (const MSet &set, FfiError &err) {
+    try
+    {
+        return set.get_max_attained();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+doccount mset_get_termfreq (const MSet &set, rust::Slice<const uint8_t> term, FfiError &err) {
+    try
+    {
+        return set.get_termfreq(slice_to_string(term));
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+double mset_get_termweight (const MSet &set, rust::Slice<const uint8_t> term, FfiError &err) {
+    try
+    {
+        return set.get_termweight(slice_to_string(term));
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+MSetHit mset_get_hit (const MSet &set, doccount index, FfiError &err) {
+    MSetHit hit {};
+    try
+    {
+        MSetIterator it = set[index];
+        hit.docid = *it;
+        hit.rank = it.get_rank();
+        hit.weight = it.get_weight();
+        hit.percent = it.get_percent();
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+
+    return hit;
+}
+
+doccount mset_get_collapse_count (const MSet &set, doccount index, FfiError &err) {
+    try
+    {
+        return set[index].get_collapse_count();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return 0;
+    }
+}
+
+std::unique_ptr<std::string> mset_get_collapse_key (const MSet &set, doccount index, FfiError &err) {
+    try
+    {
+        return std::make_unique<std::string>(set[index].get_collapse_key());
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+std::unique_ptr<std::string> mset_get_sort_key (const MSet &set, doccount index, FfiError &err) {
+    try
+    {
+        return std::make_unique<std::string>(set[index].get_sort_key());
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+/////
+
+std::unique_ptr<MultiValueKeyMaker> new_multi_value_key_maker (FfiError &err) {
+    try
+    {
+        return std::make_unique<Xapian::MultiValueKeyMaker>();
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
+    }
+}
+
+void add_value_to_multi_value_key_maker(MultiValueKeyMaker &this_m, valueno slot, bool reverse, rust::Slice<const uint8_t> defvalue, FfiError &err) {
+    try
+    {
+        this_m.add_value(slot, reverse, slice_to_string(defvalue));
+    }
+    catch (...)
+    {
+        set_error(err);
+    }
+}
+
+std::unique_ptr<KeyMaker> multi_value_key_maker_into_key_maker(std::unique_ptr<MultiValueKeyMaker> sorter) {
+    return sorter;
+}
+
+// KeyMaker calling a Rust closure with a handle on the document
+class RustKeyMaker : public KeyMaker {
+    rust::Box<KeyMakerBox> key_fn;
+
+  public:
+    explicit RustKeyMaker(rust::Box<KeyMakerBox> key_fn_) : key_fn(std::move(key_fn_)) { }
+
+    std::string operator()(const Document &doc) const {
+        rust::Vec<uint8_t> key = key_maker_call(*key_fn, std::make_unique<Document>(doc));
+        return std::string(reinterpret_cast<const char *>(key.data()), key.size());
+    }
+};
+
+std::unique_ptr<KeyMaker> new_rust_key_maker(rust::Box<KeyMakerBox> key_fn, FfiError &err) {
+    try
+    {
+        return std::make_unique<RustKeyMaker>(std::move(key_fn));
+    }
+    catch (...)
+    {
+        set_error(err);
+        return NULL;
     }
 }
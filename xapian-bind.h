@@ -7,76 +7,224 @@
 
 using namespace Xapian;
 
-std::unique_ptr<Database> new_database(int8_t &err);
-std::unique_ptr<Enquire> new_enquire(Database &db, int8_t &err);
+struct FfiError;
+struct MSetHit;
+struct WeightBox;
+struct KeyMakerBox;
+
+// Iterators handed to Rust keep their end iterator alongside the current position.
+struct TermIter
+{
+    TermIterator it;
+    TermIterator end;
+};
+
+struct ValueIter
+{
+    ValueIterator it;
+    ValueIterator end;
+};
+
+struct PostingIter
+{
+    PostingIterator it;
+    PostingIterator end;
+};
+
+struct PositionIter
+{
+    PositionIterator it;
+    PositionIterator end;
+};
+
+// Strings are returned by value in a std::unique_ptr<std::string>, never by reference
+// to storage shared between calls, so results stay valid and thread safe.
+
+std::unique_ptr<Database> new_database(FfiError &err);
+std::unique_ptr<Enquire> new_enquire(Database &db, FfiError &err);
+
+//
+std::unique_ptr<Database> new_database_with_path(rust::Str path, int32_t flags, FfiError &err);
+void database_reopen (Database &db, FfiError &err);
+void add_database(Database &db, Database &add_db, FfiError &err);
+void database_close(Database &db, FfiError &err);
+doccount database_get_doccount(const Database &db, FfiError &err);
+docid database_get_lastdocid(const Database &db, FfiError &err);
+double database_get_avlength(const Database &db, FfiError &err);
+totallength database_get_total_length(const Database &db, FfiError &err);
+termcount database_get_doclength(const Database &db, docid did, FfiError &err);
+termcount database_get_unique_terms(const Database &db, docid did, FfiError &err);
+doccount database_get_termfreq(const Database &db, rust::Slice<const uint8_t> term, FfiError &err);
+termcount database_get_collection_freq(const Database &db, rust::Slice<const uint8_t> term, FfiError &err);
+bool database_term_exists(const Database &db, rust::Slice<const uint8_t> term, FfiError &err);
+termcount database_get_doclength_lower_bound(const Database &db, FfiError &err);
+termcount database_get_doclength_upper_bound(const Database &db, FfiError &err);
+termcount database_get_wdf_upper_bound(const Database &db, rust::Slice<const uint8_t> term, FfiError &err);
+std::unique_ptr<TermIter> database_allterms(const Database &db, rust::Slice<const uint8_t> prefix, FfiError &err);
+std::unique_ptr<PostingIter> database_postlist(const Database &db, rust::Slice<const uint8_t> term, FfiError &err);
+std::unique_ptr<PositionIter> database_positionlist(const Database &db, docid did, rust::Slice<const uint8_t> term, FfiError &err);
+std::unique_ptr<Document> database_get_document(const Database &db, docid did, bool assume_valid, FfiError &err);
+std::unique_ptr<ValueIter> database_valuestream(const Database &db, valueno slot, FfiError &err);
+doccount database_get_value_freq(const Database &db, valueno slot, FfiError &err);
+std::unique_ptr<std::string> database_get_value_lower_bound(const Database &db, valueno slot, FfiError &err);
+std::unique_ptr<std::string> database_get_value_upper_bound(const Database &db, valueno slot, FfiError &err);
+
+//
+std::unique_ptr<Stem> new_stem(rust::Str lang, FfiError &err);
+
+#ifdef XAPIAN_RUSTY_TEST_THROW
+void test_throw(rust::Str kind, rust::Str msg, FfiError &err);
+#endif
+
+//
+std::unique_ptr<WritableDatabase> new_writable_database_with_flags(rust::Str path, int32_t flags, FfiError &err);
+void commit (WritableDatabase &db, FfiError &err);
+docid replace_document(WritableDatabase &db, rust::Slice<const uint8_t> unique_term, Document &doc,  FfiError &err);
+void delete_document(WritableDatabase &db, rust::Slice<const uint8_t> unique_term, FfiError &err);
+docid add_document(WritableDatabase &db, Document &doc, FfiError &err);
+void replace_document_by_id(WritableDatabase &db, docid did, Document &doc, FfiError &err);
+void delete_document_by_id(WritableDatabase &db, docid did, FfiError &err);
+void begin_transaction(WritableDatabase &db, bool flushed, FfiError &err);
+void commit_transaction(WritableDatabase &db, FfiError &err);
+void cancel_transaction(WritableDatabase &db, FfiError &err);
+doccount get_doccount (WritableDatabase &db, FfiError &err);
+std::unique_ptr<Document> writable_database_get_document(const WritableDatabase &db, docid did, bool assume_valid, FfiError &err);
+std::unique_ptr<Database> writable_database_as_database(const WritableDatabase &db, FfiError &err);
+
+//
+std::unique_ptr<TermGenerator> new_termgenerator(FfiError &err);
+void set_stemmer (TermGenerator &tg, Stem &stem, FfiError &err);
+void set_document (TermGenerator &tg, Document &doc, FfiError &err);
+void index_text_with_prefix (TermGenerator &tg, rust::Str data, rust::Str prefix, FfiError &err);
+void index_text (TermGenerator &tg, rust::Str data, FfiError &err);
+void index_int (TermGenerator &tg, int32_t data, rust::Str prefix, FfiError &err);
+void index_long (TermGenerator &tg, int64_t data, rust::Str prefix, FfiError &err);
+void index_float(TermGenerator &tg, float in_data, rust::Str prefix, FfiError &err);
+void index_double (TermGenerator &tg, double data, rust::Str prefix, FfiError &err);
+
+//
+std::unique_ptr<Document> new_document (FfiError &err);
+void add_string (Document &doc, valueno slot, rust::Slice<const uint8_t> data, FfiError &err);
+void add_int (Document &doc, valueno slot, int data, FfiError &err);
+void add_long(Document &doc, valueno slot, int64_t in_data, FfiError &err);
+void add_float(Document &doc, valueno slot, float in_data, FfiError &err);
+void add_double(Document &doc, valueno slot, double in_data, FfiError &err);
+void set_data (Document &doc, rust::Slice<const uint8_t> data, FfiError &err);
+void add_boolean_term(Document &doc, rust::Slice<const uint8_t> data, FfiError &err);
+void add_term(Document &doc, rust::Slice<const uint8_t> term, termcount wdf_inc, FfiError &err);
+void add_posting(Document &doc, rust::Slice<const uint8_t> term, termpos pos, termcount wdf_inc, FfiError &err);
+void remove_term(Document &doc, rust::Slice<const uint8_t> term, FfiError &err);
+void remove_posting(Document &doc, rust::Slice<const uint8_t> term, termpos pos, termcount wdf_dec, FfiError &err);
+termpos remove_postings(Document &doc, rust::Slice<const uint8_t> term, termpos first, termpos last, termcount wdf_dec, FfiError &err);
+void clear_terms(Document &doc, FfiError &err);
+void remove_value(Document &doc, valueno slot, FfiError &err);
+void clear_values(Document &doc, FfiError &err);
+std::unique_ptr<std::string> get_doc_data (const Document &doc, FfiError &err);
+std::unique_ptr<std::string> get_value (const Document &doc, valueno slot, FfiError &err);
+docid get_docid (const Document &doc, FfiError &err);
+termcount termlist_count (const Document &doc, FfiError &err);
+termcount values_count (const Document &doc, FfiError &err);
+std::unique_ptr<TermIter> document_termlist (const Document &doc, FfiError &err);
+std::unique_ptr<ValueIter> document_values (const Document &doc, FfiError &err);
 
 //
-std::unique_ptr<Database> new_database_with_path(rust::Str path, int8_t action, int8_t &err);
-void database_reopen (Database &db, int8_t &err);
-void add_database(Database &db, Database &add_db, int8_t &err);
-void database_close(Database &db, int8_t &err);
+bool term_iter_at_end (TermIter &iter);
+void term_iter_next (TermIter &iter, FfiError &err);
+std::unique_ptr<std::string> term_iter_term (TermIter &iter, FfiError &err);
+termcount term_iter_wdf (TermIter &iter, FfiError &err);
+doccount term_iter_termfreq (TermIter &iter, FfiError &err);
+rust::Vec<termpos> term_iter_positions (TermIter &iter, FfiError &err);
 
 //
-std::unique_ptr<Stem> new_stem(rust::Str lang, int8_t &err);
+bool value_iter_at_end (ValueIter &iter);
+void value_iter_next (ValueIter &iter, FfiError &err);
+valueno value_iter_slot (ValueIter &iter, FfiError &err);
+std::unique_ptr<std::string> value_iter_value (ValueIter &iter, FfiError &err);
+docid value_iter_docid (ValueIter &iter, FfiError &err);
+void value_iter_skip_to (ValueIter &iter, docid did, FfiError &err);
+bool value_iter_check (ValueIter &iter, docid did, FfiError &err);
 
 //
-std::unique_ptr<WritableDatabase> new_writable_database_with_path(rust::Str path, int8_t action, int8_t db_type, int8_t &err);
-void commit (WritableDatabase &db, int8_t &err);
-docid replace_document(WritableDatabase &db, rust::Str unique_term, Document &doc,  int8_t &err);
-void delete_document(WritableDatabase &db, rust::Str unique_term, int8_t &err);
-const std::string &get_db_description (WritableDatabase &db);
-int32_t get_doccount (WritableDatabase &db, int8_t &err);
+bool posting_iter_at_end (PostingIter &iter);
+void posting_iter_next (PostingIter &iter, FfiError &err);
+void posting_iter_skip_to (PostingIter &iter, docid did, FfiError &err);
+docid posting_iter_docid (PostingIter &iter, FfiError &err);
+termcount posting_iter_wdf (PostingIter &iter, FfiError &err);
+termcount posting_iter_doclength (PostingIter &iter, FfiError &err);
 
 //
-std::unique_ptr<TermGenerator> new_termgenerator(int8_t &err);
-void set_stemmer (TermGenerator &tg, Stem &stem, int8_t &err);
-void set_document (TermGenerator &tg, Document &doc, int8_t &err);
-void index_text_with_prefix (TermGenerator &tg, rust::Str data, rust::Str prefix, int8_t &err);
-void index_text (TermGenerator &tg, rust::Str data, int8_t &err);
-void index_int (TermGenerator &tg, int32_t data, rust::Str prefix, int8_t &err);
-void index_long (TermGenerator &tg, int64_t data, rust::Str prefix, int8_t &err);
-void index_float(TermGenerator &tg, float in_data, rust::Str prefix, int8_t &err);
-void index_double (TermGenerator &tg, double data, rust::Str prefix, int8_t &err);
+bool position_iter_at_end (PositionIter &iter);
+void position_iter_next (PositionIter &iter, FfiError &err);
+void position_iter_skip_to (PositionIter &iter, termpos pos, FfiError &err);
+termpos position_iter_pos (PositionIter &iter, FfiError &err);
 
 //
-std::unique_ptr<Document> new_document (int8_t &err);
-void add_string (Document &doc, valueno slot, rust::Str data, int8_t &err);
-void add_int (Document &doc, valueno slot, int data, int8_t &err);
-void add_long(Document &doc, valueno slot, int64_t in_data, int8_t &err);
-void add_float(Document &doc, valueno slot, float in_data, int8_t &err);
-void add_double(Document &doc, valueno slot, double in_data, int8_t &err);
-void set_data (Document &doc, rust::Str data, int8_t &err);
-void add_boolean_term(Document &doc, rust::Str data, int8_t &err);
-const std::string &get_doc_data (Document &doc);
+std::unique_ptr<std::string> sortable_serialise_double (double value, FfiError &err);
+double sortable_unserialise_bytes (rust::Slice<const uint8_t> serialised, FfiError &err);
 
 //
-std::unique_ptr<QueryParser> new_query_parser(int8_t &err);
-void set_max_wildcard_expansion(QueryParser &qp, int32_t limit, int8_t &err);
-void set_stemmer_to_qp(QueryParser &qp, Stem &stem, int8_t &err);
-void set_database(QueryParser &qp, Database &db, int8_t &err);
-std::unique_ptr<Query> parse_query(QueryParser &qp, rust::Str data, int16_t flags, int8_t &err);
-std::unique_ptr<Query> parse_query_with_prefix(QueryParser &qp, rust::Str query, int16_t flags, rust::Str prefix, int8_t &err);
+std::unique_ptr<QueryParser> new_query_parser(FfiError &err);
+void set_max_wildcard_expansion(QueryParser &qp, int32_t limit, FfiError &err);
+void set_stemmer_to_qp(QueryParser &qp, Stem &stem, FfiError &err);
+void set_database(QueryParser &qp, Database &db, FfiError &err);
+std::unique_ptr<Query> parse_query(QueryParser &qp, rust::Str data, int16_t flags, FfiError &err);
+std::unique_ptr<Query> parse_query_with_prefix(QueryParser &qp, rust::Str query, int16_t flags, rust::Str prefix, FfiError &err);
 
 //
-std::unique_ptr<Query> new_query(int8_t &err);
-std::unique_ptr<Query> new_query_range(int32_t op, valueno slot, double begin, double end, int8_t &err);
-std::unique_ptr<Query> new_query_double_with_prefix(rust::Str prefix, double _d, int8_t &err);
-std::unique_ptr<Query> add_right_query(Query &this_q, int32_t _op, Query &q, int8_t &err);
-bool query_is_empty (Query &q, int8_t &err);
-const std::string &get_description (Query &q);
+std::unique_ptr<Query> new_query_range(int32_t op, valueno slot, double begin, double end, FfiError &err);
+std::unique_ptr<Query> new_query_double_with_prefix(rust::Str prefix, double _d, FfiError &err);
+std::unique_ptr<Query> add_right_query(Query &this_q, int32_t _op, Query &q, FfiError &err);
+bool query_is_empty (Query &q, FfiError &err);
+std::unique_ptr<std::string> get_description (Query &q, FfiError &err);
 
 //
-std::unique_ptr<MSet> get_mset(Enquire &en, int32_t from, int32_t size, int8_t &err);
-void set_query(Enquire &en, Query &query, int8_t &err);
-void set_sort_by_key(Enquire &en, MultiValueKeyMaker & sorter, bool reverse, int8_t &err);
+std::unique_ptr<MSet> get_mset(Enquire &en, int32_t from, int32_t size, FfiError &err);
+void set_query(Enquire &en, Query &query, FfiError &err);
+void set_sort_by_key(Enquire &en, KeyMaker &sorter, bool reverse, FfiError &err);
+void set_sort_by_key_then_relevance(Enquire &en, KeyMaker &sorter, bool reverse, FfiError &err);
+void set_sort_by_relevance_then_key(Enquire &en, KeyMaker &sorter, bool reverse, FfiError &err);
+void set_sort_by_value(Enquire &en, valueno slot, bool reverse, FfiError &err);
+void set_sort_by_value_then_relevance(Enquire &en, valueno slot, bool reverse, FfiError &err);
+void set_sort_by_relevance_then_value(Enquire &en, valueno slot, bool reverse, FfiError &err);
+void set_sort_by_relevance(Enquire &en, FfiError &err);
+void set_docid_order(Enquire &en, int32_t order, FfiError &err);
+void set_collapse_key(Enquire &en, valueno slot, doccount collapse_max, FfiError &err);
+void set_weighting_bool(Enquire &en, FfiError &err);
+void set_weighting_coord(Enquire &en, FfiError &err);
+void set_weighting_tfidf(Enquire &en, rust::Str normalizations, FfiError &err);
+void set_weighting_bm25(Enquire &en, double k1, double k2, double k3, double b, double min_normlen, FfiError &err);
+void set_weighting_bm25plus(Enquire &en, double k1, double k2, double k3, double b, double min_normlen, double delta, FfiError &err);
+void set_weighting_trad(Enquire &en, double k, FfiError &err);
+void set_weighting_inl2(Enquire &en, double c, FfiError &err);
+void set_weighting_ifb2(Enquire &en, double c, FfiError &err);
+void set_weighting_ineb2(Enquire &en, double c, FfiError &err);
+void set_weighting_bb2(Enquire &en, double c, FfiError &err);
+void set_weighting_dlh(Enquire &en, FfiError &err);
+void set_weighting_pl2(Enquire &en, double c, FfiError &err);
+void set_weighting_pl2plus(Enquire &en, double c, double delta, FfiError &err);
+void set_weighting_dph(Enquire &en, FfiError &err);
+void set_weighting_lm(Enquire &en, double param_log, int32_t smoothing, double param_smoothing1, double param_smoothing2, FfiError &err);
+void set_weighting_rust(Enquire &en, rust::Box<WeightBox> weight, FfiError &err);
 
 //
-int get_matches_estimated (MSet &set, int8_t &err);
-int mset_size (MSet &set, int8_t &err);
-std::unique_ptr<Document> get_doc_by_index (MSet &set, int32_t index, int8_t &err);
+int get_matches_estimated (const MSet &set, FfiError &err);
+doccount mset_size (const MSet &set, FfiError &err);
+std::unique_ptr<Document> get_doc_by_index (const MSet &set, doccount index, FfiError &err);
+doccount get_matches_lower_bound (const MSet &set, FfiError &err);
+doccount get_matches_upper_bound (const MSet &set, FfiError &err);
+double get_max_possible (const MSet &set, FfiError &err);
+double get_max_attained (const MSet &set, FfiError &err);
+doccount mset_get_termfreq (const MSet &set, rust::Slice<const uint8_t> term, FfiError &err);
+double mset_get_termweight (const MSet &set, rust::Slice<const uint8_t> term, FfiError &err);
+MSetHit mset_get_hit (const MSet &set, doccount index, FfiError &err);
+doccount mset_get_collapse_count (const MSet &set, doccount index, FfiError &err);
+std::unique_ptr<std::string> mset_get_collapse_key (const MSet &set, doccount index, FfiError &err);
+std::unique_ptr<std::string> mset_get_sort_key (const MSet &set, doccount index, FfiError &err);
 
 //
-std::unique_ptr<MultiValueKeyMaker> new_multi_value_key_maker (int8_t &err);
-void add_value_to_multi_value_key_maker(MultiValueKeyMaker &this_m, valueno slot, bool asc_desc, int8_t &err);
+std::unique_ptr<MultiValueKeyMaker> new_multi_value_key_maker (FfiError &err);
+void add_value_to_multi_value_key_maker(MultiValueKeyMaker &this_m, valueno slot, bool reverse, rust::Slice<const uint8_t> defvalue, FfiError &err);
+std::unique_ptr<KeyMaker> multi_value_key_maker_into_key_maker(std::unique_ptr<MultiValueKeyMaker> sorter);
+std::unique_ptr<KeyMaker> new_rust_key_maker(rust::Box<KeyMakerBox> key_fn, FfiError &err);
 
 